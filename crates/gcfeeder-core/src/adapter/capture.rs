@@ -0,0 +1,179 @@
+//! Raw adapter payload captures.
+//!
+//! A capture starts with the magic bytes `GCAP`, a format version byte and the
+//! captured adapter's id as a bus byte, a port count byte and the port path.
+//! Each report follows as the microseconds since the previous report, as a
+//! little-endian `u32`, and the 37 payload bytes.
+
+use std::{
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+use super::{AdapterId, Payload, PAYLOAD_LEN};
+
+const MAGIC: &[u8; 4] = b"GCAP";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Time since the first report of the capture.
+    pub offset: Duration,
+    pub payload: Payload,
+}
+
+pub struct Writer<W: Write> {
+    writer: W,
+    last: Option<Instant>,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(mut writer: W, id: &AdapterId) -> io::Result<Self> {
+        let port_count = u8::try_from(id.ports.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "port path too long"))?;
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, id.bus, port_count])?;
+        writer.write_all(&id.ports)?;
+
+        Ok(Self { writer, last: None })
+    }
+
+    pub fn write(&mut self, time: Instant, payload: &Payload) -> io::Result<()> {
+        let delta = self
+            .last
+            .map(|last| time.saturating_duration_since(last))
+            .unwrap_or_default();
+        let micros = u32::try_from(delta.as_micros()).unwrap_or(u32::MAX);
+        self.last = Some(time);
+
+        self.writer.write_all(&micros.to_le_bytes())?;
+        self.writer.write_all(payload)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub struct Reader<R: Read> {
+    reader: R,
+    id: AdapterId,
+    offset: Duration,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0_u8; MAGIC.len() + 3];
+        reader.read_exact(&mut header)?;
+
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an adapter capture",
+            ));
+        }
+
+        let [version, bus, port_count] = [header[4], header[5], header[6]];
+        if version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported capture version {}", version),
+            ));
+        }
+
+        let mut ports = vec![0_u8; usize::from(port_count)];
+        reader.read_exact(&mut ports)?;
+
+        Ok(Self {
+            reader,
+            id: AdapterId::new(bus, ports),
+            offset: Duration::ZERO,
+        })
+    }
+
+    /// The id of the adapter the capture was recorded from.
+    pub const fn id(&self) -> &AdapterId {
+        &self.id
+    }
+
+    /// Reads the next record, returning `None` at the end of the capture.
+    pub fn read_record(&mut self) -> io::Result<Option<Record>> {
+        let mut micros = [0_u8; 4];
+        match self.reader.read_exact(&mut micros) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let mut payload = [0_u8; PAYLOAD_LEN];
+        self.reader.read_exact(&mut payload)?;
+
+        self.offset += Duration::from_micros(u64::from(u32::from_le_bytes(micros)));
+
+        Ok(Some(Record {
+            offset: self.offset,
+            payload,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::adapter::{AdapterId, PAYLOAD_LEN};
+
+    use super::{Reader, Record, Writer};
+
+    #[test]
+    fn capture_round_trips() {
+        let id = AdapterId::new(3, vec![1, 2]);
+        let start = Instant::now();
+        let records = [
+            Record {
+                offset: Duration::ZERO,
+                payload: [0x21; PAYLOAD_LEN],
+            },
+            Record {
+                offset: Duration::from_micros(8_000),
+                payload: [0x10; PAYLOAD_LEN],
+            },
+            Record {
+                offset: Duration::from_micros(16_250),
+                payload: [0xFF; PAYLOAD_LEN],
+            },
+        ];
+
+        let mut writer = Writer::new(Vec::new(), &id).unwrap();
+        for record in records.iter() {
+            writer
+                .write(start + record.offset, &record.payload)
+                .unwrap();
+        }
+        let bytes = writer.into_inner();
+        assert_eq!(bytes.len(), 4 + 3 + 2 + records.len() * (4 + PAYLOAD_LEN));
+
+        let reader = Reader::new(bytes.as_slice()).unwrap();
+        assert_eq!(*reader.id(), id);
+        let read = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(read, records);
+    }
+
+    #[test]
+    fn capture_rejects_other_files() {
+        assert!(Reader::new(&b"not a capture"[..]).is_err());
+    }
+}
@@ -1,21 +1,27 @@
-use std::{ops::ControlFlow, result, time::Duration};
+use std::{fmt, result, time::Duration};
 
-use enum_iterator::{all, cardinality, Sequence};
-use gcinput::{Input, Rumble, Stick};
+use enum_iterator::{cardinality, Sequence};
+use gcinput::{codec, Input, Rumble};
 use log::info;
-use rusb::{DeviceHandle, UsbContext};
 use thiserror::Error;
 
+use quirks::Validation;
+
+pub use codec::{ControllerKind, ControllerStatus, Payload, PAYLOAD_LEN};
+pub use poller::InputMessage;
+pub use transport::Transport;
+
+pub mod capture;
 pub mod poller;
+pub mod quirks;
+pub mod transport;
 
 const VID: u16 = 0x057E;
 const PID: u16 = 0x0337;
 
-const PAYLOAD_LEN: usize = 37;
-
 const ALLOWED_TIMEOUT: Duration = Duration::from_millis(16);
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum Error {
     #[error("usb error: {0}")]
     Usb(#[from] rusb::Error),
@@ -27,131 +33,102 @@ pub enum Error {
 
 pub type Result<T> = result::Result<T, Error>;
 
-struct Endpoints {
-    pub in_: u8,
-    pub out: u8,
+/// Identifies an adapter by the USB bus and port path it is plugged into,
+/// which stays the same across reconnects to the same physical port.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AdapterId {
+    pub bus: u8,
+    pub ports: Vec<u8>,
 }
 
-pub struct Adapter<T: UsbContext> {
-    handle: rusb::DeviceHandle<T>,
-    endpoints: Endpoints,
+impl AdapterId {
+    pub const fn new(bus: u8, ports: Vec<u8>) -> Self {
+        Self { bus, ports }
+    }
 }
 
-impl<T: UsbContext> Adapter<T> {
-    pub fn open(context: &T) -> Result<Self> {
-        let mut handle = Self::find_and_open_device(context)?;
+impl fmt::Display for AdapterId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.bus)?;
 
-        handle.claim_interface(0)?;
+        for (i, port) in self.ports.iter().enumerate() {
+            let sep = if i == 0 { '-' } else { '.' };
+            write!(f, "{}{}", sep, port)?;
+        }
 
-        let endpoints = Self::find_endpoints(&handle)?;
+        Ok(())
+    }
+}
 
-        // From Dolphin:
-        // This call makes Nyko-brand (and perhaps other) adapters work.
-        // However it returns LIBUSB_ERROR_PIPE with Mayflash adapters.
-        let _ = handle.write_control(0x21, 11, 0x0001, 0, &[], Duration::from_secs(1))?;
+/// Addresses a single controller by the adapter and port it is plugged into.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ControllerId {
+    pub adapter: AdapterId,
+    pub port: Port,
+}
+
+pub struct Adapter<T: Transport> {
+    id: AdapterId,
+    transport: T,
+}
+
+impl<T: Transport> Adapter<T> {
+    pub fn open(context: &T::Context, id: &AdapterId) -> Result<Self> {
+        let transport = T::open(context, id)?;
+
+        info!("Connected to adapter {}", id);
+
+        Ok(Self {
+            id: id.clone(),
+            transport,
+        })
+    }
 
-        // Not sure what this does but Dolphin does it
-        let _ = handle.write_interrupt(endpoints.out, &[0x13], ALLOWED_TIMEOUT)?;
+    pub const fn id(&self) -> &AdapterId {
+        &self.id
+    }
 
-        info!("Connected to adapter");
+    pub fn read_inputs(&self) -> Result<[InputMessage; Port::COUNT]> {
+        self.decode(&self.read_payload()?)
+    }
 
-        Ok(Self { handle, endpoints })
+    /// Decodes `payload` as validated by this adapter's transport.
+    pub fn decode(&self, payload: &Payload) -> Result<[InputMessage; Port::COUNT]> {
+        match self.transport.validation() {
+            Validation::Strict => decode_payload(payload),
+            Validation::Length => Ok(inputs_from_payload(payload)),
+        }
     }
 
-    pub fn read_inputs(&self) -> Result<[Option<Input>; Port::COUNT]> {
+    /// Reads one full payload without checking that it holds inputs.
+    pub fn read_payload(&self) -> Result<Payload> {
         let mut payload = [0_u8; PAYLOAD_LEN];
-        let bytes_read =
-            self.handle
-                .read_interrupt(self.endpoints.in_, &mut payload, ALLOWED_TIMEOUT)?;
+        let bytes_read = self.transport.read_payload(&mut payload)?;
 
-        if bytes_read == PAYLOAD_LEN && payload[0] == rusb::constants::LIBUSB_DT_HID {
-            Ok(inputs_from_payload(&payload))
+        if bytes_read == PAYLOAD_LEN {
+            Ok(payload)
         } else {
             Err(Error::InvalidPayload)
         }
     }
 
     pub fn write_rumble(&self, states: [Rumble; Port::COUNT]) -> Result<()> {
-        let payload = [
-            0x11,
-            states[0].into(),
-            states[1].into(),
-            states[2].into(),
-            states[3].into(),
-        ];
-
-        _ = self
-            .handle
-            .write_interrupt(self.endpoints.out, &payload, ALLOWED_TIMEOUT)?;
-
-        Ok(())
+        self.transport.write_rumble(states)
     }
 
     pub fn reset_rumble(&self) -> Result<()> {
-        self.write_rumble([Rumble::Off; Port::COUNT])
-    }
-
-    fn find_and_open_device(context: &T) -> Result<DeviceHandle<T>> {
-        let handle = context
-            .devices()?
-            .iter()
-            .filter(|device| {
-                device
-                    .device_descriptor()
-                    .map(|descriptor| {
-                        descriptor.vendor_id() == VID && descriptor.product_id() == PID
-                    })
-                    .unwrap_or(false)
-            })
-            .try_fold(Err(Error::NoDevice), |acc, device| {
-                if acc.is_err() {
-                    ControlFlow::Continue(device.open().map_err(Error::Usb))
-                } else {
-                    ControlFlow::Break(acc)
-                }
-            });
-
-        match handle {
-            ControlFlow::Continue(c) => c,
-            ControlFlow::Break(b) => b,
-        }
-    }
-
-    /// Returns (`in_endpoint`, `out_endpoint`) if found, and if not, an error.
-    fn find_endpoints(handle: &rusb::DeviceHandle<T>) -> rusb::Result<Endpoints> {
-        let device = handle.device();
-        let config = device.config_descriptor(0)?;
-
-        let mut in_ = 0_u8;
-        let mut out = 0_u8;
-
-        for iface in config.interfaces() {
-            for descriptor in iface.descriptors() {
-                for endpoint in descriptor.endpoint_descriptors() {
-                    match endpoint.direction() {
-                        rusb::Direction::In => {
-                            in_ = endpoint.address();
-                        }
-                        rusb::Direction::Out => {
-                            out = endpoint.address();
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(Endpoints { in_, out })
+        self.transport.reset()
     }
 }
 
-impl<T: UsbContext> Drop for Adapter<T> {
+impl<T: Transport> Drop for Adapter<T> {
     fn drop(&mut self) {
         let _ = self.reset_rumble();
-        info!("Disconnected from adapter");
+        info!("Disconnected from adapter {}", self.id);
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Sequence)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Sequence)]
 pub enum Port {
     One,
     Two,
@@ -199,42 +176,12 @@ pub enum FromPortError {
     OutOfRange,
 }
 
-fn inputs_from_payload(payload: &[u8; PAYLOAD_LEN]) -> [Option<Input>; Port::COUNT] {
-    let mut inputs = [None; Port::COUNT];
-
-    for port in all::<Port>() {
-        let index = port.index();
-        // type is 0 if no controller is plugged, 1 if wired, and 2 if wireless
-        let controller_type = payload[1 + (9 * index)] >> 4;
-        let connected = controller_type != 0;
-
-        if connected {
-            let b1 = payload[1 + (9 * index) + 1];
-            let b2 = payload[1 + (9 * index) + 2];
-
-            inputs[index] = Some(Input {
-                button_a: (b1 & (1 << 0)) != 0,
-                button_b: (b1 & (1 << 1)) != 0,
-                button_x: (b1 & (1 << 2)) != 0,
-                button_y: (b1 & (1 << 3)) != 0,
-
-                button_left: (b1 & (1 << 4)) != 0,
-                button_right: (b1 & (1 << 5)) != 0,
-                button_down: (b1 & (1 << 6)) != 0,
-                button_up: (b1 & (1 << 7)) != 0,
-
-                button_start: (b2 & (1 << 0)) != 0,
-                button_z: (b2 & (1 << 1)) != 0,
-                button_r: (b2 & (1 << 2)) != 0,
-                button_l: (b2 & (1 << 3)) != 0,
-
-                main_stick: Stick::new(payload[1 + (9 * index) + 3], payload[1 + (9 * index) + 4]),
-                c_stick: Stick::new(payload[1 + (9 * index) + 5], payload[1 + (9 * index) + 6]),
-                left_trigger: payload[1 + (9 * index) + 7],
-                right_trigger: payload[1 + (9 * index) + 8],
-            });
-        }
-    }
+pub fn decode_payload(payload: &Payload) -> Result<[InputMessage; Port::COUNT]> {
+    codec::decode(payload)
+        .map(|reports| reports.map(InputMessage::from))
+        .map_err(|_| Error::InvalidPayload)
+}
 
-    inputs
+fn inputs_from_payload(payload: &Payload) -> [InputMessage; Port::COUNT] {
+    codec::decode_ports(payload).map(InputMessage::from)
 }
@@ -1,5 +1,7 @@
 use std::{
-    array, mem,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::{self, Write},
+    mem,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, Mutex,
@@ -8,32 +10,119 @@ use std::{
     time::{Duration, Instant},
 };
 
-use crossbeam::atomic::AtomicCell;
+use crossbeam::{atomic::AtomicCell, channel};
 use enclose::enclose;
+use enum_iterator::all;
+use gcinput::codec::PortReport;
 use log::warn;
-use rusb::UsbContext;
 
 use crate::util::{
+    metrics::{Histogram, Summary},
     recent_channel::{self as recent, TrySendError},
     AverageTimer,
 };
 
-use super::{Adapter, Input, Port, Rumble};
+use super::{
+    capture, transport::Hotplug, Adapter, AdapterId, ControllerId, ControllerKind,
+    ControllerStatus, Error, Input, Port, Rumble, Transport,
+};
+
+/// A single port's share of an adapter report.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct InputMessage {
+    pub input: Option<Input>,
+    pub status: ControllerStatus,
+}
+
+impl From<PortReport> for InputMessage {
+    fn from(report: PortReport) -> Self {
+        Self {
+            input: report.input,
+            status: report.status,
+        }
+    }
+}
 
-pub type InputMessage = Option<Input>;
+type SenderData = (recent::Sender<InputMessage>, Arc<Subscription>);
 
-type SenderData = (recent::Sender<InputMessage>, Port);
+type CaptureWriter = capture::Writer<Box<dyn Write + Send>>;
 
 pub const ERROR_TIMEOUT: Duration = Duration::from_millis(8);
 
-pub struct Poller<T: UsbContext + 'static> {
+/// How long to wait before trying to open an adapter again after it failed to open.
+pub const OPEN_RETRY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Polls slower than two report intervals of an adapter at its stock 125Hz
+/// let a report go by, and are counted as overruns.
+pub const POLL_BUDGET: Duration = Duration::from_millis(16);
+
+#[derive(Debug, Clone)]
+pub enum ConnectionEvent {
+    AdapterAttached(AdapterId),
+    AdapterDetached(AdapterId),
+    /// Sent once each time an adapter starts failing to open, rather than on every retry.
+    OpenFailed {
+        adapter: AdapterId,
+        error: Error,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControllerEvent {
+    ControllerConnected {
+        time: Instant,
+        controller: ControllerId,
+        kind: ControllerKind,
+    },
+    ControllerDisconnected {
+        time: Instant,
+        controller: ControllerId,
+    },
+}
+
+impl ControllerEvent {
+    pub const fn time(&self) -> Instant {
+        match self {
+            Self::ControllerConnected { time, .. } | Self::ControllerDisconnected { time, .. } => {
+                *time
+            }
+        }
+    }
+
+    pub const fn controller(&self) -> &ControllerId {
+        match self {
+            Self::ControllerConnected { controller, .. }
+            | Self::ControllerDisconnected { controller, .. } => controller,
+        }
+    }
+}
+
+/// The controller a `Listener` receives inputs from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// A port on whichever connected adapter attached first, which stays the
+    /// same while other adapters come and go.
+    Primary(Port),
+    Controller(ControllerId),
+}
+
+impl Target {
+    pub const fn port(&self) -> Port {
+        match self {
+            Self::Primary(port) => *port,
+            Self::Controller(ControllerId { port, .. }) => *port,
+        }
+    }
+}
+
+pub struct Poller<T: Transport + 'static> {
     context: Arc<Context<T>>,
     thread: Option<JoinHandle<()>>,
 }
 
-impl<T: UsbContext + 'static> Poller<T> {
-    pub fn new(usb_context: T) -> Self {
-        let context = Arc::new(Context::new(usb_context));
+impl<T: Transport + 'static> Poller<T> {
+    pub fn new(transport_context: T::Context) -> Self {
+        let context = Arc::new(Context::new(transport_context));
         let thread = thread::spawn(enclose!((context) move || context.poll_loop()));
 
         Self {
@@ -42,28 +131,146 @@ impl<T: UsbContext + 'static> Poller<T> {
         }
     }
 
+    /// Returns the context adapters are enumerated and opened with.
+    pub fn transport_context(&self) -> &T::Context {
+        &self.context.transport_context
+    }
+
+    /// Returns the average poll time of the primary adapter.
     #[must_use]
     pub fn average_poll_time(&self) -> Option<Duration> {
-        *self.context.average_poll_time.lock().unwrap()
+        let primary = self.context.primary()?;
+        self.adapter_poll_time(&primary)
+    }
+
+    #[must_use]
+    pub fn adapter_poll_time(&self, id: &AdapterId) -> Option<Duration> {
+        let adapters = self.context.adapters.lock().unwrap();
+        adapters.get(id).and_then(|stats| stats.poll_time)
+    }
+
+    /// Returns the distribution of adapter `id`'s poll times since it connected.
+    #[must_use]
+    pub fn adapter_poll_times(&self, id: &AdapterId) -> Option<Summary> {
+        let adapters = self.context.adapters.lock().unwrap();
+        adapters
+            .get(id)
+            .and_then(|stats| stats.poll_times.summary())
+    }
+
+    /// Forgets the poll times recorded so far for every adapter.
+    pub fn clear_poll_times(&self) {
+        let mut adapters = self.context.adapters.lock().unwrap();
+        for stats in adapters.values_mut() {
+            stats.poll_times.clear();
+        }
+    }
+
+    /// Returns the measured average time between reports from adapter `id`.
+    #[must_use]
+    pub fn report_interval(&self, id: &AdapterId) -> Option<Duration> {
+        self.context.report_interval(id)
     }
 
+    /// Returns `true` if at least one adapter is connected.
     #[must_use]
     pub fn connected(&self) -> bool {
-        self.context.connected.load(Ordering::Acquire)
+        !self.context.adapters.lock().unwrap().is_empty()
+    }
+
+    /// Returns the connected adapters in bus/port path order.
+    #[must_use]
+    pub fn adapters(&self) -> Vec<AdapterId> {
+        self.context
+            .adapters
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
     }
 
+    /// Subscribes to adapters being attached, detached or failing to open.
+    pub fn connection_events(&self) -> channel::Receiver<ConnectionEvent> {
+        let (sender, receiver) = channel::unbounded();
+        self.context.connection_senders.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Subscribes to controllers being plugged into or unplugged from any adapter.
+    pub fn controller_events(&self) -> channel::Receiver<ControllerEvent> {
+        let (sender, receiver) = channel::unbounded();
+        self.context.controller_senders.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Starts writing every raw report from adapter `id` to `writer` in the
+    /// `capture` format, replacing any capture already running for it.
+    pub fn start_capture(
+        &self,
+        id: &AdapterId,
+        writer: impl Write + Send + 'static,
+    ) -> io::Result<()> {
+        let writer = capture::Writer::new(Box::new(writer) as Box<dyn Write + Send>, id)?;
+        let old = self
+            .context
+            .captures
+            .lock()
+            .unwrap()
+            .insert(id.clone(), writer);
+
+        match old {
+            Some(mut old) => old.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops capturing adapter `id` and flushes what was captured.
+    pub fn stop_capture(&self, id: &AdapterId) -> io::Result<()> {
+        let writer = self.context.captures.lock().unwrap().remove(id);
+
+        match writer {
+            Some(mut writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    #[must_use]
+    pub fn is_capturing(&self, id: &AdapterId) -> bool {
+        self.context.captures.lock().unwrap().contains_key(id)
+    }
+
+    /// Listens to `port` on the primary adapter.
     pub fn add_listener(&self, port: Port) -> Listener<T> {
+        self.add_target_listener(Target::Primary(port))
+    }
+
+    pub fn add_controller_listener(&self, controller: ControllerId) -> Listener<T> {
+        self.add_target_listener(Target::Controller(controller))
+    }
+
+    pub fn add_target_listener(&self, target: Target) -> Listener<T> {
         let (sender, receiver) = recent::channel();
-        self.context.senders.lock().unwrap().push((sender, port));
+        let subscription = Arc::new(Subscription {
+            target,
+            rumble: Default::default(),
+        });
+
+        self.context
+            .senders
+            .lock()
+            .unwrap()
+            .push((sender, subscription.clone()));
+
         Listener {
             receiver,
+            subscription,
             context: self.context.clone(),
-            port,
         }
     }
 }
 
-impl<T: UsbContext> Drop for Poller<T> {
+impl<T: Transport> Drop for Poller<T> {
     fn drop(&mut self) {
         self.context.stop_flag.store(true, Ordering::Release);
 
@@ -73,114 +280,378 @@ impl<T: UsbContext> Drop for Poller<T> {
     }
 }
 
-struct Context<T: UsbContext> {
+struct Subscription {
+    pub target: Target,
+    pub rumble: AtomicCell<Rumble>,
+}
+
+impl Subscription {
+    /// Returns the port this subscription listens to on `adapter`, if any.
+    pub fn port_on(&self, adapter: &AdapterId, primary: Option<&AdapterId>) -> Option<Port> {
+        match &self.target {
+            Target::Primary(port) => (primary == Some(adapter)).then_some(*port),
+            Target::Controller(c) => (c.adapter == *adapter).then_some(c.port),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AdapterStats {
+    pub attached_at: Instant,
+    pub poll_time: Option<Duration>,
+    pub poll_times: Histogram,
+    pub report_interval: Option<Duration>,
+}
+
+impl Default for AdapterStats {
+    fn default() -> Self {
+        Self {
+            attached_at: Instant::now(),
+            poll_time: None,
+            poll_times: Histogram::new(POLL_BUDGET),
+            report_interval: None,
+        }
+    }
+}
+
+struct Context<T: Transport> {
     pub stop_flag: AtomicBool,
-    pub connected: AtomicBool,
-    pub usb_context: T,
-    pub rumble_states: [AtomicCell<Rumble>; Port::COUNT],
+    pub transport_context: T::Context,
     pub senders: Mutex<Vec<SenderData>>,
-    pub average_poll_time: Mutex<Option<Duration>>,
+    pub connection_senders: Mutex<Vec<channel::Sender<ConnectionEvent>>>,
+    pub controller_senders: Mutex<Vec<channel::Sender<ControllerEvent>>>,
+    pub adapters: Mutex<BTreeMap<AdapterId, AdapterStats>>,
+    pub captures: Mutex<HashMap<AdapterId, CaptureWriter>>,
 }
 
-impl<T: UsbContext> Context<T> {
-    pub fn new(usb_context: T) -> Self {
+impl<T: Transport> Context<T> {
+    pub fn new(transport_context: T::Context) -> Self {
         Self {
             stop_flag: Default::default(),
-            connected: Default::default(),
-            usb_context,
-            rumble_states: Default::default(),
+            transport_context,
             senders: Default::default(),
-            average_poll_time: Default::default(),
+            connection_senders: Default::default(),
+            controller_senders: Default::default(),
+            adapters: Default::default(),
+            captures: Default::default(),
+        }
+    }
+
+    pub fn poll_loop(self: Arc<Self>)
+    where
+        T: 'static,
+    {
+        let (hotplug_sender, hotplug_receiver) = channel::unbounded();
+        let watch = T::watch(&self.transport_context, hotplug_sender).unwrap_or_else(|e| {
+            warn!("Failed to watch for adapters, polling instead: {}", e);
+            None
+        });
+
+        let mut present: BTreeSet<AdapterId> = BTreeSet::new();
+        let mut failed: HashMap<AdapterId, Instant> = HashMap::new();
+        let mut threads: HashMap<AdapterId, JoinHandle<()>> = HashMap::new();
+
+        while !self.stop_flag.load(Ordering::Acquire) {
+            if let Some(watch) = watch.as_ref() {
+                if let Err(e) = watch.handle_events(ERROR_TIMEOUT) {
+                    warn!("Failed to handle adapter events: {}", e);
+                    thread::sleep(ERROR_TIMEOUT);
+                }
+
+                for event in hotplug_receiver.try_iter() {
+                    match event {
+                        Hotplug::Arrived(id) => {
+                            present.insert(id);
+                        }
+                        Hotplug::Left(id) => {
+                            present.remove(&id);
+                        }
+                    }
+                }
+            } else {
+                match T::enumerate(&self.transport_context) {
+                    Ok(ids) => present = ids.into_iter().collect(),
+                    Err(e) => warn!("Failed to enumerate adapters: {}", e),
+                }
+
+                thread::sleep(ERROR_TIMEOUT);
+            }
+
+            threads.retain(|_, thread| !thread.is_finished());
+            failed.retain(|id, _| present.contains(id));
+
+            for id in present.iter() {
+                let retry_pending = failed
+                    .get(id)
+                    .is_some_and(|t| t.elapsed() < OPEN_RETRY_TIMEOUT);
+
+                if threads.contains_key(id) || retry_pending {
+                    continue;
+                }
+
+                match Adapter::<T>::open(&self.transport_context, id) {
+                    Ok(adapter) => {
+                        failed.remove(id);
+                        self.connect(id);
+                        threads.insert(
+                            id.clone(),
+                            thread::spawn(
+                                enclose!((self => context) move || context.adapter_loop(adapter)),
+                            ),
+                        );
+                    }
+                    Err(e) => {
+                        if failed.insert(id.clone(), Instant::now()).is_none() {
+                            warn!("Failed to connect to adapter {}: {}", id, e);
+                            self.send_connection_event(ConnectionEvent::OpenFailed {
+                                adapter: id.clone(),
+                                error: e,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        for (_, thread) in threads.drain() {
+            mem::drop(thread.join());
         }
     }
 
-    pub fn poll_loop(&self) {
+    fn adapter_loop(&self, adapter: Adapter<T>) {
+        let id = adapter.id().clone();
         let thread_pool = rayon::ThreadPoolBuilder::new()
             .num_threads(2)
             .build()
             .unwrap();
-        let mut adapter: Option<Adapter<T>> = None;
         let mut timer = AverageTimer::start(0.9).unwrap();
+        let mut report_timer = AverageTimer::start(0.9).unwrap();
+        let mut reported = false;
+        let mut kinds = [ControllerKind::None; Port::COUNT];
 
         while !self.stop_flag.load(Ordering::Acquire) {
-            let result = {
-                let adapter = match self.adapter_or_reload(&mut adapter) {
-                    Ok(a) => a,
-                    Err(e) => {
-                        warn!("Failed to connect to adapter: {}", e);
-                        thread::sleep(ERROR_TIMEOUT);
-                        continue;
-                    }
-                };
+            timer.reset();
+            let (input, rumble) = thread_pool.join(
+                || self.process_input(&adapter),
+                || self.process_rumble(&adapter),
+            );
+
+            match rumble.and(input) {
+                Ok(messages) => {
+                    self.update_controllers(&id, &mut kinds, messages.map(|m| m.status.kind));
+                }
+                Err(Error::Usb(rusb::Error::Timeout)) => continue,
+                Err(e) => {
+                    warn!("Adapter {} error: {}", id, e);
+                    break;
+                }
+            }
 
-                timer.reset();
-                let (input, rumble) = thread_pool.join(
-                    || self.process_input(adapter),
-                    || self.process_rumble(adapter),
-                );
+            let report_interval = reported.then(|| report_timer.lap());
+            report_timer.reset();
+            reported = true;
 
-                input.and(rumble)
+            if let Some(stats) = self.adapters.lock().unwrap().get_mut(&id) {
+                stats.poll_times.record(timer.read());
+                stats.poll_time = Some(timer.lap());
+                stats.report_interval = report_interval.or(stats.report_interval);
+            }
+        }
+
+        mem::drop(adapter);
+        self.update_controllers(&id, &mut kinds, [ControllerKind::None; Port::COUNT]);
+        self.disconnect(&id);
+    }
+
+    fn send_connection_event(&self, event: ConnectionEvent) {
+        broadcast(&self.connection_senders, event);
+    }
+
+    /// Sends controller events for every port whose controller kind changed.
+    fn update_controllers(
+        &self,
+        adapter: &AdapterId,
+        kinds: &mut [ControllerKind; Port::COUNT],
+        new_kinds: [ControllerKind; Port::COUNT],
+    ) {
+        let time = Instant::now();
+
+        for port in all::<Port>() {
+            let (old, new) = (kinds[port.index()], new_kinds[port.index()]);
+            if old == new {
+                continue;
+            }
+
+            let controller = ControllerId {
+                adapter: adapter.clone(),
+                port,
             };
 
-            match result {
-                Err(super::Error::Usb(rusb::Error::Timeout)) => continue,
-                Err(e) => {
-                    adapter = None;
-                    warn!("Adapter error: {}", e);
-                    continue;
-                }
-                _ => (),
+            if old != ControllerKind::None {
+                broadcast(
+                    &self.controller_senders,
+                    ControllerEvent::ControllerDisconnected {
+                        time,
+                        controller: controller.clone(),
+                    },
+                );
             }
 
-            *self.average_poll_time.lock().unwrap() = Some(timer.lap());
+            if new != ControllerKind::None {
+                broadcast(
+                    &self.controller_senders,
+                    ControllerEvent::ControllerConnected {
+                        time,
+                        controller,
+                        kind: new,
+                    },
+                );
+            }
         }
 
-        self.connected.store(false, Ordering::Release);
+        *kinds = new_kinds;
+    }
+
+    fn connect(&self, id: &AdapterId) {
+        self.adapters
+            .lock()
+            .unwrap()
+            .insert(id.clone(), AdapterStats::default());
+        self.send_connection_event(ConnectionEvent::AdapterAttached(id.clone()));
+    }
+
+    fn report_interval(&self, id: &AdapterId) -> Option<Duration> {
+        let adapters = self.adapters.lock().unwrap();
+        adapters.get(id).and_then(|stats| stats.report_interval)
+    }
+
+    /// The adapter that has been attached the longest.
+    fn primary(&self) -> Option<AdapterId> {
+        let adapters = self.adapters.lock().unwrap();
+        adapters
+            .iter()
+            .min_by_key(|(_, stats)| stats.attached_at)
+            .map(|(id, _)| id.clone())
     }
 
-    fn process_input(&self, adapter: &Adapter<T>) -> super::Result<()> {
-        let inputs = adapter.read_inputs()?;
+    fn process_input(&self, adapter: &Adapter<T>) -> super::Result<[InputMessage; Port::COUNT]> {
+        let payload = adapter.read_payload()?;
+        self.capture(adapter.id(), &payload);
+
+        let messages = adapter.decode(&payload)?;
+        let primary = self.primary();
         let mut senders = self.senders.lock().unwrap();
 
-        senders.retain(|(sender, port)| {
-            let index = port.index();
-            !matches!(
-                sender.try_send(inputs[index]),
-                Err(TrySendError::Disconnected(_))
-            )
+        senders.retain(|(sender, subscription)| {
+            match subscription.port_on(adapter.id(), primary.as_ref()) {
+                Some(port) => !matches!(
+                    sender.try_send(messages[port.index()]),
+                    Err(TrySendError::Disconnected(_))
+                ),
+                None => true,
+            }
         });
 
-        Ok(())
+        Ok(messages)
+    }
+
+    fn capture(&self, id: &AdapterId, payload: &super::Payload) {
+        let mut captures = self.captures.lock().unwrap();
+
+        if let Some(writer) = captures.get_mut(id) {
+            if let Err(e) = writer.write(Instant::now(), payload) {
+                warn!("Stopped capturing adapter {}: {}", id, e);
+                captures.remove(id);
+            }
+        }
     }
 
     fn process_rumble(&self, adapter: &Adapter<T>) -> super::Result<()> {
-        adapter.write_rumble(array::from_fn(|i| self.rumble_states[i].load()))
+        let mut states = [Rumble::Off; Port::COUNT];
+        let primary = self.primary();
+
+        for (_, subscription) in self.senders.lock().unwrap().iter() {
+            if let Some(port) = subscription.port_on(adapter.id(), primary.as_ref()) {
+                if matches!(subscription.rumble.load(), Rumble::On) {
+                    states[port.index()] = Rumble::On;
+                }
+            }
+        }
+
+        adapter.write_rumble(states)
     }
 
-    fn adapter_or_reload<'a>(
-        &self,
-        adapter: &'a mut Option<Adapter<T>>,
-    ) -> super::Result<&'a mut Adapter<T>> {
-        if let Some(adapter) = adapter {
-            Ok(adapter)
-        } else {
-            self.connected.store(false, Ordering::Release);
-            let adapter = Ok(adapter.insert(Adapter::open(&self.usb_context)?));
-            self.connected.store(true, Ordering::Release);
-            adapter
+    /// Forgets `id` and tells its listeners that their controllers are gone.
+    fn disconnect(&self, id: &AdapterId) {
+        let primary = self.primary();
+        self.adapters.lock().unwrap().remove(id);
+
+        for (sender, subscription) in self.senders.lock().unwrap().iter() {
+            if subscription.port_on(id, primary.as_ref()).is_some() {
+                let _ = sender.try_send(InputMessage::default());
+            }
         }
+
+        self.send_connection_event(ConnectionEvent::AdapterDetached(id.clone()));
     }
 }
 
-pub struct Listener<T: UsbContext> {
+fn broadcast<E: Clone>(senders: &Mutex<Vec<channel::Sender<E>>>, event: E) {
+    senders
+        .lock()
+        .unwrap()
+        .retain(|sender| sender.send(event.clone()).is_ok());
+}
+
+pub struct Listener<T: Transport> {
     receiver: recent::Receiver<InputMessage>,
+    subscription: Arc<Subscription>,
     context: Arc<Context<T>>,
-    port: Port,
 }
 
-impl<T: UsbContext> Listener<T> {
-    pub const fn port(&self) -> Port {
-        self.port
+impl<T: Transport> Listener<T> {
+    pub fn port(&self) -> Port {
+        self.subscription.target.port()
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.subscription.target
+    }
+
+    /// Returns the adapter currently serving this listener, if it is connected.
+    pub fn adapter(&self) -> Option<AdapterId> {
+        match &self.subscription.target {
+            Target::Primary(_) => self.context.primary(),
+            Target::Controller(c) => self
+                .context
+                .adapters
+                .lock()
+                .unwrap()
+                .contains_key(&c.adapter)
+                .then(|| c.adapter.clone()),
+        }
+    }
+
+    /// Returns the controller currently serving this listener, if its adapter is connected.
+    pub fn controller(&self) -> Option<ControllerId> {
+        Some(ControllerId {
+            adapter: self.adapter()?,
+            port: self.port(),
+        })
+    }
+
+    /// Returns whether this listener follows the first attached adapter.
+    pub fn on_primary(&self) -> bool {
+        match &self.subscription.target {
+            Target::Primary(_) => true,
+            Target::Controller(c) => self.context.primary().as_ref() == Some(&c.adapter),
+        }
+    }
+
+    /// Returns the measured average time between reports from this listener's adapter.
+    pub fn report_interval(&self) -> Option<Duration> {
+        self.adapter()
+            .and_then(|adapter| self.context.report_interval(&adapter))
     }
 
     pub fn recv(&self) -> Result<InputMessage, recent::RecvError> {
@@ -206,10 +677,280 @@ impl<T: UsbContext> Listener<T> {
     }
 
     pub fn set_rumble(&self, rumble: Rumble) {
-        self.context.rumble_states[self.port.index()].store(rumble);
+        self.subscription.rumble.store(rumble);
     }
 
     pub fn reset_rumble(&self) {
         self.set_rumble(Rumble::Off)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Write},
+        mem,
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::adapter::{
+        transport::{
+            scripted::Step, Replay, ReplayTransport, Script, ScriptedBus, ScriptedTransport,
+            Transport,
+        },
+        AdapterId, ControllerId, ControllerKind, Payload, Port, PAYLOAD_LEN,
+    };
+
+    use super::{ConnectionEvent, ControllerEvent, Poller};
+
+    /// Builds a payload with a wired controller in port one and `b1` as its first button byte.
+    fn payload(b1: u8) -> Payload {
+        let mut payload = [0_u8; PAYLOAD_LEN];
+        payload[0] = rusb::constants::LIBUSB_DT_HID;
+        payload[1..10].copy_from_slice(&[0x10, b1, 0x00, 0x80, 0x80, 0x80, 0x80, 0x00, 0x00]);
+        payload
+    }
+
+    fn script(id: AdapterId, payload: Payload) -> Script {
+        let script = Script::new(id).with_interval(Duration::from_millis(1));
+        for _ in 0..64 {
+            script.push_payload(payload);
+        }
+        script
+    }
+
+    #[test]
+    fn poller_reads_scripted_payloads() {
+        let script = script(AdapterId::new(1, vec![1]), payload(0x01));
+
+        let poller = Poller::<ScriptedTransport>::new(ScriptedBus::from(script.clone()));
+        let one = poller.add_listener(Port::One);
+        let two = poller.add_listener(Port::Two);
+
+        let message = one
+            .recv_timeout(Duration::from_secs(1))
+            .expect("expected a report for port one");
+        let input = message.input.expect("expected port one to be connected");
+        assert!(input.button_a);
+        assert!(!input.button_b);
+        assert_eq!(message.status.kind, ControllerKind::Wired);
+        assert!(!message.status.rumble_powered);
+
+        let message = two
+            .recv_timeout(Duration::from_secs(1))
+            .expect("expected a report for port two");
+        assert!(message.input.is_none());
+        assert_eq!(message.status.kind, ControllerKind::None);
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let interval = loop {
+            let _ = one.recv_timeout(Duration::from_secs(1));
+            if let Some(interval) = one.report_interval() {
+                break interval;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "report interval was never measured"
+            );
+        };
+        assert!(interval >= Duration::from_millis(1));
+
+        assert!(poller.connected());
+        assert_eq!(script.open_count(), 1);
+    }
+
+    #[test]
+    fn poller_addresses_multiple_adapters() {
+        let first = AdapterId::new(1, vec![2]);
+        let second = AdapterId::new(1, vec![3, 1]);
+
+        let bus = ScriptedBus::with_hotplug();
+        let poller = Poller::<ScriptedTransport>::new(bus.clone());
+        let events = poller.connection_events();
+        let primary = poller.add_listener(Port::One);
+        let other = poller.add_controller_listener(ControllerId {
+            adapter: first.clone(),
+            port: Port::One,
+        });
+
+        // Attach out of path order to check that the primary adapter stays the
+        // one attached first.
+        bus.attach(script(second.clone(), payload(0x02)));
+        match events.recv_timeout(Duration::from_secs(1)) {
+            Ok(ConnectionEvent::AdapterAttached(attached)) => assert_eq!(attached, second),
+            e => panic!("expected adapter to attach, got {:?}", e),
+        }
+        bus.attach(script(first.clone(), payload(0x01)));
+
+        let recv = |listener: &super::Listener<ScriptedTransport>| {
+            listener
+                .recv_timeout(Duration::from_secs(1))
+                .expect("expected a report")
+                .input
+                .expect("expected a controller")
+        };
+
+        assert!(recv(&other).button_a);
+        for _ in 0..8 {
+            assert!(recv(&primary).button_b);
+        }
+
+        assert_eq!(poller.adapters(), vec![first, second]);
+    }
+
+    #[test]
+    fn poller_follows_hotplug_events() {
+        let id = AdapterId::new(2, vec![4]);
+        let bus = ScriptedBus::with_hotplug();
+        let poller = Poller::<ScriptedTransport>::new(bus.clone());
+        let events = poller.connection_events();
+
+        let script = script(id.clone(), payload(0x01));
+        bus.attach(script.clone());
+
+        match events.recv_timeout(Duration::from_secs(1)) {
+            Ok(ConnectionEvent::AdapterAttached(attached)) => assert_eq!(attached, id),
+            e => panic!("expected adapter to attach, got {:?}", e),
+        }
+
+        script.push(Step::Disconnect);
+        bus.detach(&id);
+
+        // The disconnect step is queued behind the remaining payloads.
+        match events.recv_timeout(Duration::from_secs(5)) {
+            Ok(ConnectionEvent::AdapterDetached(detached)) => assert_eq!(detached, id),
+            e => panic!("expected adapter to detach, got {:?}", e),
+        }
+
+        assert!(!poller.connected());
+        assert_eq!(script.open_count(), 1);
+    }
+
+    #[test]
+    fn poller_sends_controller_events() {
+        let id = AdapterId::new(1, vec![1]);
+        let mut unplugged = payload(0x00);
+        unplugged[1] = 0x00;
+
+        let script = Script::new(id.clone()).with_interval(Duration::from_millis(1));
+        script.extend([unplugged, payload(0x00), payload(0x00), unplugged].map(Step::Payload));
+
+        let bus = ScriptedBus::new();
+        let poller = Poller::<ScriptedTransport>::new(bus.clone());
+        let events = poller.controller_events();
+        bus.attach(script);
+
+        let controller = ControllerId {
+            adapter: id,
+            port: Port::One,
+        };
+
+        let connected = events.recv_timeout(Duration::from_secs(1));
+        assert!(
+            matches!(
+                &connected,
+                Ok(ControllerEvent::ControllerConnected { controller: c, kind: ControllerKind::Wired, .. })
+                    if *c == controller
+            ),
+            "expected controller to connect, got {:?}",
+            connected
+        );
+
+        let disconnected = events.recv_timeout(Duration::from_secs(1));
+        assert!(
+            matches!(
+                &disconnected,
+                Ok(ControllerEvent::ControllerDisconnected { controller: c, .. }) if *c == controller
+            ),
+            "expected controller to disconnect, got {:?}",
+            disconnected
+        );
+
+        assert!(connected.unwrap().time() <= disconnected.unwrap().time());
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn poller_replays_captured_payloads() {
+        let id = AdapterId::new(1, vec![5]);
+        let script = Script::new(id.clone()).with_interval(Duration::from_millis(1));
+        script.extend((0..32).map(|_| Step::Payload(payload(0x01))));
+        script.extend((0..32).map(|_| Step::Payload(payload(0x02))));
+
+        let buffer = SharedBuffer::default();
+        let bus = ScriptedBus::new();
+        let poller = Poller::<ScriptedTransport>::new(bus.clone());
+        // Captures can be started before the adapter shows up.
+        poller.start_capture(&id, buffer.clone()).unwrap();
+        assert!(poller.is_capturing(&id));
+        bus.attach(script.clone());
+
+        // Every read is captured, each as a 4 byte delay and the payload.
+        let captured_len = 8 + 64 * (4 + PAYLOAD_LEN);
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while buffer.0.lock().unwrap().len() < captured_len {
+            assert!(Instant::now() < deadline, "script was never captured");
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(script.is_finished());
+        poller.stop_capture(&id).unwrap();
+        assert!(!poller.is_capturing(&id));
+        mem::drop(poller);
+
+        let bytes = buffer.0.lock().unwrap().clone();
+        assert_eq!(bytes.len(), captured_len);
+        let replay = Replay::from_reader(bytes.as_slice())
+            .unwrap()
+            .with_speed(f64::INFINITY);
+        assert_eq!(*replay.id(), id);
+        assert_eq!(replay.len(), 64);
+
+        // The replay reads back 32 presses of A then 32 of B, then runs out.
+        let transport = ReplayTransport::open(&replay, &id).unwrap();
+        let mut replayed = [0_u8; PAYLOAD_LEN];
+        for expected in [payload(0x01), payload(0x02)] {
+            for _ in 0..32 {
+                transport.read_payload(&mut replayed).unwrap();
+                assert_eq!(replayed, expected);
+            }
+        }
+        assert!(replay.is_finished());
+        assert!(transport.read_payload(&mut replayed).is_err());
+
+        // Replaying through a poller starts over from a fresh copy of the capture.
+        let replay = Replay::from_reader(bytes.as_slice())
+            .unwrap()
+            .with_speed(2.0);
+        let poller = Poller::<ReplayTransport>::new(replay.clone());
+        let listener = poller.add_listener(Port::One);
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        loop {
+            assert!(Instant::now() < deadline, "capture was never replayed");
+            let input = listener
+                .recv_timeout(Duration::from_secs(1))
+                .expect("expected a report")
+                .input
+                .expect("expected a controller");
+            if input.button_b {
+                break;
+            }
+            assert!(input.button_a);
+        }
+    }
+}
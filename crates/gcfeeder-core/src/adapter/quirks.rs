@@ -0,0 +1,200 @@
+//! Differences between the official adapter and its clones.
+
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+
+use super::{PID, VID};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Sequence)]
+#[serde(rename_all = "snake_case")]
+pub enum Family {
+    Official,
+    Mayflash,
+    Nyko,
+    /// An adapter with the official ids that could not be identified.
+    Generic,
+}
+
+impl Family {
+    pub const fn quirks(self) -> Quirks {
+        match self {
+            Self::Official => Quirks {
+                handshake: Handshake::Dolphin,
+                tolerated_errors: &[],
+                endpoints: EndpointSelection::Last,
+                validation: Validation::Strict,
+            },
+            // Mayflash adapters reject the control request with `LIBUSB_ERROR_PIPE`.
+            Self::Mayflash => Quirks {
+                handshake: Handshake::Dolphin,
+                tolerated_errors: &[rusb::Error::Pipe],
+                endpoints: EndpointSelection::Last,
+                validation: Validation::Strict,
+            },
+            // Dolphin sends the control request because it "makes Nyko-brand (and
+            // perhaps other) adapters work" (Source/Core/InputCommon/GCAdapter.cpp).
+            // The protocol only uses interrupt transfers, as Dolphin's reads and
+            // writes do, so the interrupt endpoints are taken rather than whatever
+            // the descriptor lists last.
+            Self::Nyko => Quirks {
+                handshake: Handshake::Dolphin,
+                tolerated_errors: &[],
+                endpoints: EndpointSelection::FirstInterrupt,
+                validation: Validation::Strict,
+            },
+            // An unidentified clone could be any of the above, so it tolerates the
+            // errors of every known one and takes its endpoints like Nyko adapters
+            // do. Timeouts are tolerated too since the control request only matters
+            // to Nyko adapters, and payloads are only checked for length in case
+            // the clone starts them with something other than the HID descriptor type.
+            Self::Generic => Quirks {
+                handshake: Handshake::Dolphin,
+                tolerated_errors: &[rusb::Error::Pipe, rusb::Error::Timeout],
+                endpoints: EndpointSelection::FirstInterrupt,
+                validation: Validation::Length,
+            },
+        }
+    }
+
+    /// Looks up the family of a device in the quirk table, returning `None` if
+    /// it is not an adapter at all.
+    ///
+    /// Descriptor strings are matched case-insensitively and may be missing if
+    /// they could not be read.
+    pub fn detect(
+        vendor_id: u16,
+        product_id: u16,
+        manufacturer: Option<&str>,
+        product: Option<&str>,
+    ) -> Option<Self> {
+        QUIRK_TABLE
+            .iter()
+            .find(|entry| entry.matches(vendor_id, product_id, manufacturer, product))
+            .map(|entry| entry.family)
+    }
+
+    /// Returns `true` if some family in the quirk table uses these ids.
+    pub fn is_adapter(vendor_id: u16, product_id: u16) -> bool {
+        QUIRK_TABLE
+            .iter()
+            .any(|entry| entry.vendor_id == vendor_id && entry.product_id == product_id)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Quirks {
+    pub handshake: Handshake,
+    /// Errors from the handshake that do not prevent the adapter from working.
+    pub tolerated_errors: &'static [rusb::Error],
+    pub endpoints: EndpointSelection,
+    pub validation: Validation,
+}
+
+impl Quirks {
+    pub fn tolerates(&self, error: rusb::Error) -> bool {
+        self.tolerated_errors.contains(&error)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Handshake {
+    /// Dolphin's control request followed by the `0x13` start command.
+    Dolphin,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EndpointSelection {
+    /// The last IN and OUT endpoints of any transfer type.
+    Last,
+    /// The first IN and OUT interrupt endpoints.
+    FirstInterrupt,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Validation {
+    /// Payloads must be full length and start with the HID descriptor type.
+    Strict,
+    /// Payloads only need to be full length.
+    Length,
+}
+
+struct QuirkEntry {
+    vendor_id: u16,
+    product_id: u16,
+    manufacturer: Option<&'static str>,
+    product: Option<&'static str>,
+    family: Family,
+}
+
+impl QuirkEntry {
+    fn matches(
+        &self,
+        vendor_id: u16,
+        product_id: u16,
+        manufacturer: Option<&str>,
+        product: Option<&str>,
+    ) -> bool {
+        fn contains(s: Option<&str>, pattern: Option<&str>) -> bool {
+            match pattern {
+                Some(p) => s.is_some_and(|s| s.to_ascii_lowercase().contains(p)),
+                None => true,
+            }
+        }
+
+        self.vendor_id == vendor_id
+            && self.product_id == product_id
+            && contains(manufacturer, self.manufacturer)
+            && contains(product, self.product)
+    }
+}
+
+/// Checked in order, so more specific entries must come first.
+const QUIRK_TABLE: &[QuirkEntry] = &[
+    QuirkEntry {
+        vendor_id: VID,
+        product_id: PID,
+        manufacturer: Some("mayflash"),
+        product: None,
+        family: Family::Mayflash,
+    },
+    QuirkEntry {
+        vendor_id: VID,
+        product_id: PID,
+        manufacturer: Some("nyko"),
+        product: None,
+        family: Family::Nyko,
+    },
+    QuirkEntry {
+        vendor_id: VID,
+        product_id: PID,
+        manufacturer: Some("nintendo"),
+        product: None,
+        family: Family::Official,
+    },
+    QuirkEntry {
+        vendor_id: VID,
+        product_id: PID,
+        manufacturer: None,
+        product: None,
+        family: Family::Generic,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{Family, PID, VID};
+
+    #[test]
+    fn detect_uses_descriptor_strings() {
+        let detect = |manufacturer| Family::detect(VID, PID, manufacturer, Some("WUP-028"));
+
+        assert_eq!(detect(Some("Nintendo")), Some(Family::Official));
+        assert_eq!(detect(Some("MAYFLASH")), Some(Family::Mayflash));
+        assert_eq!(detect(Some("Nyko")), Some(Family::Nyko));
+        assert_eq!(detect(Some("Unknown")), Some(Family::Generic));
+        assert_eq!(detect(None), Some(Family::Generic));
+        assert_eq!(Family::detect(0x1234, 0x5678, Some("Nintendo"), None), None);
+        assert!(Family::Mayflash.quirks().tolerates(rusb::Error::Pipe));
+        assert!(!Family::Official.quirks().tolerates(rusb::Error::Pipe));
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use crossbeam::channel;
+use gcinput::Rumble;
+
+use super::{quirks::Validation, AdapterId, Payload, Port, Result};
+
+pub mod replay;
+pub mod scripted;
+pub mod usb;
+
+pub use replay::{Replay, ReplayTransport};
+pub use scripted::{Script, ScriptedBus, ScriptedTransport};
+pub use usb::{UsbBus, UsbTransport};
+
+/// An adapter arriving or leaving, as reported by a `Watch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hotplug {
+    Arrived(AdapterId),
+    Left(AdapterId),
+}
+
+/// Keeps hotplug notifications flowing for as long as it is alive.
+pub trait Watch: Send {
+    /// Processes pending notifications, waiting at most `timeout` for one to arrive.
+    fn handle_events(&self, timeout: Duration) -> Result<()>;
+}
+
+/// A source of raw adapter reports that `Adapter` and `Poller` can run on top of.
+pub trait Transport: Sized + Send + Sync {
+    /// Shared state needed to (re)open the transport, such as a USB context.
+    type Context: Send + Sync;
+
+    /// Lists every adapter currently reachable through `context`.
+    fn enumerate(context: &Self::Context) -> Result<Vec<AdapterId>>;
+
+    /// Starts sending adapter arrivals and removals to `sender`, including
+    /// adapters that are already connected.
+    ///
+    /// Returns `None` if the transport cannot watch for adapters on this
+    /// platform, in which case `enumerate` is polled instead.
+    fn watch(
+        _context: &Self::Context,
+        _sender: channel::Sender<Hotplug>,
+    ) -> Result<Option<Box<dyn Watch>>> {
+        Ok(None)
+    }
+
+    fn open(context: &Self::Context, id: &AdapterId) -> Result<Self>;
+
+    /// Reads one interrupt payload, returning the number of bytes written to `payload`.
+    fn read_payload(&self, payload: &mut Payload) -> Result<usize>;
+
+    /// How strictly payloads read from this transport are checked.
+    fn validation(&self) -> Validation {
+        Validation::Strict
+    }
+
+    fn write_rumble(&self, states: [Rumble; Port::COUNT]) -> Result<()>;
+
+    /// Returns the device to its idle state with every rumble motor off.
+    fn reset(&self) -> Result<()>;
+}
@@ -0,0 +1,139 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use gcinput::Rumble;
+
+use super::Transport;
+use crate::adapter::{
+    capture::{Reader, Record},
+    AdapterId, Error, Payload, Port, Result, ALLOWED_TIMEOUT, PAYLOAD_LEN,
+};
+
+/// A capture loaded into memory, ready to be replayed by a `ReplayTransport`.
+///
+/// Clones share the playback position, so reopening the adapter resumes the
+/// capture instead of restarting it.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    id: AdapterId,
+    records: Arc<[Record]>,
+    speed: f64,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next: usize,
+    start: Option<Instant>,
+}
+
+impl Replay {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader(reader: impl Read) -> io::Result<Self> {
+        let mut reader = Reader::new(reader)?;
+        let id = reader.id().clone();
+        let records = reader.by_ref().collect::<io::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            id,
+            records: records.into(),
+            speed: 1.0,
+            state: Default::default(),
+        })
+    }
+
+    /// Plays the capture `speed` times faster than it was recorded.
+    ///
+    /// `f64::INFINITY` replays every report as soon as it is read.
+    #[must_use]
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        assert!(speed > 0.0, "replay speed must be positive");
+        self.speed = speed;
+        self
+    }
+
+    pub const fn id(&self) -> &AdapterId {
+        &self.id
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().next >= self.records.len()
+    }
+}
+
+/// A `Transport` that plays back a recorded capture as a single adapter.
+///
+/// Once the capture runs out, reads time out as if the adapter stopped reporting.
+pub struct ReplayTransport {
+    replay: Replay,
+}
+
+impl Transport for ReplayTransport {
+    type Context = Replay;
+
+    fn enumerate(replay: &Replay) -> Result<Vec<AdapterId>> {
+        Ok(vec![replay.id.clone()])
+    }
+
+    fn open(replay: &Replay, id: &AdapterId) -> Result<Self> {
+        if *id != replay.id {
+            return Err(Error::NoDevice);
+        }
+
+        Ok(Self {
+            replay: replay.clone(),
+        })
+    }
+
+    fn read_payload(&self, payload: &mut Payload) -> Result<usize> {
+        let (record, start) = {
+            let mut state = self.replay.state.lock().unwrap();
+            let record = self.replay.records.get(state.next).copied();
+            state.next += usize::from(record.is_some());
+            (record, *state.start.get_or_insert_with(Instant::now))
+        };
+
+        let record = match record {
+            Some(r) => r,
+            None => {
+                thread::sleep(ALLOWED_TIMEOUT);
+                return Err(Error::Usb(rusb::Error::Timeout));
+            }
+        };
+
+        let due = start + Duration::from_secs_f64(record.offset.as_secs_f64() / self.replay.speed);
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+
+        *payload = record.payload;
+        Ok(PAYLOAD_LEN)
+    }
+
+    fn write_rumble(&self, _states: [Rumble; Port::COUNT]) -> Result<()> {
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crossbeam::channel;
+use gcinput::Rumble;
+
+use super::{Hotplug, Transport, Watch};
+use crate::adapter::{AdapterId, Error, Payload, Port, Result, ALLOWED_TIMEOUT, PAYLOAD_LEN};
+
+#[derive(Debug, Copy, Clone)]
+pub enum Step {
+    /// Delivers a payload to the next read.
+    Payload(Payload),
+    /// Makes the next read time out.
+    Timeout,
+    /// Fails the next read as if the adapter was unplugged.
+    Disconnect,
+}
+
+/// An in-memory sequence of adapter reports shared between a test and the
+/// `ScriptedTransport`s opened from it.
+#[derive(Debug, Clone)]
+pub struct Script {
+    id: AdapterId,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    steps: VecDeque<Step>,
+    interval: Duration,
+    rumble_writes: Vec<[Rumble; Port::COUNT]>,
+    open_count: usize,
+}
+
+impl Script {
+    pub fn new(id: AdapterId) -> Self {
+        Self {
+            id,
+            state: Default::default(),
+        }
+    }
+
+    pub const fn id(&self) -> &AdapterId {
+        &self.id
+    }
+
+    /// Delays every read by `interval` to mimic the adapter's report rate.
+    #[must_use]
+    pub fn with_interval(self, interval: Duration) -> Self {
+        self.state.lock().unwrap().interval = interval;
+        self
+    }
+
+    pub fn push(&self, step: Step) {
+        self.state.lock().unwrap().steps.push_back(step);
+    }
+
+    pub fn push_payload(&self, payload: Payload) {
+        self.push(Step::Payload(payload));
+    }
+
+    pub fn extend(&self, steps: impl IntoIterator<Item = Step>) {
+        self.state.lock().unwrap().steps.extend(steps);
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().steps.is_empty()
+    }
+
+    #[must_use]
+    pub fn open_count(&self) -> usize {
+        self.state.lock().unwrap().open_count
+    }
+
+    #[must_use]
+    pub fn rumble_writes(&self) -> Vec<[Rumble; Port::COUNT]> {
+        self.state.lock().unwrap().rumble_writes.clone()
+    }
+}
+
+/// The set of scripted adapters a `ScriptedTransport` can enumerate and open.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedBus {
+    scripts: Arc<Mutex<Vec<Script>>>,
+    watchers: Option<Arc<Mutex<Vec<channel::Sender<Hotplug>>>>>,
+}
+
+impl ScriptedBus {
+    /// Creates a bus that has to be enumerated to find adapters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a bus that reports attached and detached scripts as hotplug events.
+    pub fn with_hotplug() -> Self {
+        Self {
+            scripts: Default::default(),
+            watchers: Some(Default::default()),
+        }
+    }
+
+    pub fn attach(&self, script: Script) {
+        let id = script.id.clone();
+
+        {
+            let mut scripts = self.scripts.lock().unwrap();
+            scripts.retain(|s| s.id != script.id);
+            scripts.push(script);
+        }
+
+        self.notify(Hotplug::Arrived(id));
+    }
+
+    pub fn detach(&self, id: &AdapterId) {
+        self.scripts.lock().unwrap().retain(|s| s.id != *id);
+        self.notify(Hotplug::Left(id.clone()));
+    }
+
+    fn notify(&self, event: Hotplug) {
+        if let Some(watchers) = self.watchers.as_ref() {
+            watchers
+                .lock()
+                .unwrap()
+                .retain(|sender| sender.send(event.clone()).is_ok());
+        }
+    }
+}
+
+struct ScriptedWatch;
+
+impl Watch for ScriptedWatch {
+    fn handle_events(&self, timeout: Duration) -> Result<()> {
+        // Events are sent as soon as scripts are attached or detached.
+        thread::sleep(timeout);
+        Ok(())
+    }
+}
+
+impl From<Script> for ScriptedBus {
+    fn from(script: Script) -> Self {
+        let bus = Self::new();
+        bus.attach(script);
+        bus
+    }
+}
+
+/// A `Transport` that plays back a `Script` instead of talking to a device.
+///
+/// Once the script runs out, reads time out as if the adapter stopped reporting.
+pub struct ScriptedTransport {
+    script: Script,
+}
+
+impl Transport for ScriptedTransport {
+    type Context = ScriptedBus;
+
+    fn enumerate(bus: &ScriptedBus) -> Result<Vec<AdapterId>> {
+        Ok(bus
+            .scripts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|s| s.id.clone())
+            .collect())
+    }
+
+    fn watch(
+        bus: &ScriptedBus,
+        sender: channel::Sender<Hotplug>,
+    ) -> Result<Option<Box<dyn Watch>>> {
+        let watchers = match bus.watchers.as_ref() {
+            Some(w) => w,
+            None => return Ok(None),
+        };
+
+        for script in bus.scripts.lock().unwrap().iter() {
+            let _ = sender.send(Hotplug::Arrived(script.id.clone()));
+        }
+
+        watchers.lock().unwrap().push(sender);
+
+        Ok(Some(Box::new(ScriptedWatch)))
+    }
+
+    fn open(bus: &ScriptedBus, id: &AdapterId) -> Result<Self> {
+        let scripts = bus.scripts.lock().unwrap();
+        let script = scripts
+            .iter()
+            .find(|s| s.id == *id)
+            .ok_or(Error::NoDevice)?;
+
+        script.state.lock().unwrap().open_count += 1;
+
+        Ok(Self {
+            script: script.clone(),
+        })
+    }
+
+    fn read_payload(&self, payload: &mut Payload) -> Result<usize> {
+        let (step, interval) = {
+            let mut state = self.script.state.lock().unwrap();
+            (state.steps.pop_front(), state.interval)
+        };
+
+        if !interval.is_zero() {
+            thread::sleep(interval);
+        }
+
+        match step {
+            Some(Step::Payload(p)) => {
+                *payload = p;
+                Ok(PAYLOAD_LEN)
+            }
+            Some(Step::Disconnect) => Err(Error::Usb(rusb::Error::NoDevice)),
+            Some(Step::Timeout) | None => {
+                thread::sleep(ALLOWED_TIMEOUT);
+                Err(Error::Usb(rusb::Error::Timeout))
+            }
+        }
+    }
+
+    fn write_rumble(&self, states: [Rumble; Port::COUNT]) -> Result<()> {
+        self.script.state.lock().unwrap().rumble_writes.push(states);
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<()> {
+        self.write_rumble([Rumble::Off; Port::COUNT])
+    }
+}
@@ -0,0 +1,274 @@
+use std::{sync::Arc, time::Duration};
+
+use crossbeam::{atomic::AtomicCell, channel};
+use gcinput::{codec, Rumble};
+use log::info;
+use rusb::{Device, DeviceHandle, HotplugBuilder, Registration, UsbContext};
+
+use super::{Hotplug, Transport, Watch};
+use crate::adapter::{
+    quirks::{EndpointSelection, Family, Handshake, Quirks, Validation},
+    AdapterId, Error, Payload, Port, Result, ALLOWED_TIMEOUT,
+};
+
+struct Endpoints {
+    pub in_: u8,
+    pub out: u8,
+}
+
+/// A libusb context along with the quirk family to use instead of detecting it.
+///
+/// Clones share the override, so it can be changed after handing the bus to a `Poller`.
+#[derive(Debug, Clone)]
+pub struct UsbBus<T: UsbContext> {
+    context: T,
+    quirks: Arc<AtomicCell<Option<Family>>>,
+}
+
+impl<T: UsbContext> UsbBus<T> {
+    pub fn new(context: T) -> Self {
+        Self {
+            context,
+            quirks: Default::default(),
+        }
+    }
+
+    pub const fn context(&self) -> &T {
+        &self.context
+    }
+
+    #[must_use]
+    pub fn quirks_override(&self) -> Option<Family> {
+        self.quirks.load()
+    }
+
+    /// Uses `family`'s quirks for adapters opened from now on, or detects them if `None`.
+    pub fn set_quirks_override(&self, family: Option<Family>) {
+        self.quirks.store(family);
+    }
+}
+
+pub struct UsbTransport<T: UsbContext> {
+    handle: DeviceHandle<T>,
+    endpoints: Endpoints,
+    quirks: Quirks,
+}
+
+impl<T: UsbContext> UsbTransport<T> {
+    fn is_adapter(device: &Device<T>) -> bool {
+        device
+            .device_descriptor()
+            .map(|descriptor| Family::is_adapter(descriptor.vendor_id(), descriptor.product_id()))
+            .unwrap_or(false)
+    }
+
+    fn adapter_devices(context: &T) -> Result<Vec<Device<T>>> {
+        Ok(context.devices()?.iter().filter(Self::is_adapter).collect())
+    }
+
+    fn adapter_id(device: &Device<T>) -> Result<AdapterId> {
+        Ok(AdapterId::new(device.bus_number(), device.port_numbers()?))
+    }
+
+    fn detect_family(handle: &DeviceHandle<T>) -> Result<Family> {
+        let descriptor = handle.device().device_descriptor()?;
+        let manufacturer = handle.read_manufacturer_string_ascii(&descriptor).ok();
+        let product = handle.read_product_string_ascii(&descriptor).ok();
+
+        Family::detect(
+            descriptor.vendor_id(),
+            descriptor.product_id(),
+            manufacturer.as_deref(),
+            product.as_deref(),
+        )
+        .ok_or(Error::NoDevice)
+    }
+
+    /// Returns (`in_endpoint`, `out_endpoint`) if found, and if not, an error.
+    fn find_endpoints(
+        handle: &DeviceHandle<T>,
+        selection: EndpointSelection,
+    ) -> rusb::Result<Endpoints> {
+        let device = handle.device();
+        let config = device.config_descriptor(0)?;
+
+        let mut in_ = None;
+        let mut out = None;
+
+        for iface in config.interfaces() {
+            for descriptor in iface.descriptors() {
+                for endpoint in descriptor.endpoint_descriptors() {
+                    let found = match endpoint.direction() {
+                        rusb::Direction::In => &mut in_,
+                        rusb::Direction::Out => &mut out,
+                    };
+
+                    match selection {
+                        EndpointSelection::Last => *found = Some(endpoint.address()),
+                        EndpointSelection::FirstInterrupt => {
+                            if found.is_none()
+                                && endpoint.transfer_type() == rusb::TransferType::Interrupt
+                            {
+                                *found = Some(endpoint.address());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        match (selection, in_, out) {
+            (_, Some(in_), Some(out)) => Ok(Endpoints { in_, out }),
+            // Keep the historical behavior of falling back to endpoint zero.
+            (EndpointSelection::Last, in_, out) => Ok(Endpoints {
+                in_: in_.unwrap_or(0),
+                out: out.unwrap_or(0),
+            }),
+            (EndpointSelection::FirstInterrupt, _, _) => Err(rusb::Error::NotFound),
+        }
+    }
+
+    fn handshake(&self) -> Result<()> {
+        let result = match self.quirks.handshake {
+            Handshake::Dolphin => {
+                // From Dolphin:
+                // This call makes Nyko-brand (and perhaps other) adapters work.
+                // However it returns LIBUSB_ERROR_PIPE with Mayflash adapters.
+                self.handle
+                    .write_control(0x21, 11, 0x0001, 0, &[], Duration::from_secs(1))
+                    .map(|_| ())
+            }
+        };
+
+        match result {
+            Err(e) if !self.quirks.tolerates(e) => return Err(e.into()),
+            _ => {}
+        }
+
+        // Not sure what this does but Dolphin does it
+        let _ = self.handle.write_interrupt(
+            self.endpoints.out,
+            &[codec::START_COMMAND],
+            ALLOWED_TIMEOUT,
+        )?;
+
+        Ok(())
+    }
+}
+
+struct HotplugHandler {
+    sender: channel::Sender<Hotplug>,
+}
+
+impl<T: UsbContext> rusb::Hotplug<T> for HotplugHandler {
+    fn device_arrived(&mut self, device: Device<T>) {
+        if !UsbTransport::is_adapter(&device) {
+            return;
+        }
+
+        if let Ok(id) = UsbTransport::<T>::adapter_id(&device) {
+            let _ = self.sender.send(Hotplug::Arrived(id));
+        }
+    }
+
+    fn device_left(&mut self, device: Device<T>) {
+        if !UsbTransport::is_adapter(&device) {
+            return;
+        }
+
+        if let Ok(id) = UsbTransport::<T>::adapter_id(&device) {
+            let _ = self.sender.send(Hotplug::Left(id));
+        }
+    }
+}
+
+struct UsbWatch<T: UsbContext> {
+    context: T,
+    _registration: Registration<T>,
+}
+
+impl<T: UsbContext> Watch for UsbWatch<T> {
+    fn handle_events(&self, timeout: Duration) -> Result<()> {
+        Ok(self.context.handle_events(Some(timeout))?)
+    }
+}
+
+impl<T: UsbContext + 'static> Transport for UsbTransport<T> {
+    type Context = UsbBus<T>;
+
+    fn enumerate(bus: &UsbBus<T>) -> Result<Vec<AdapterId>> {
+        Self::adapter_devices(&bus.context)?
+            .iter()
+            .map(Self::adapter_id)
+            .collect()
+    }
+
+    fn watch(bus: &UsbBus<T>, sender: channel::Sender<Hotplug>) -> Result<Option<Box<dyn Watch>>> {
+        if !rusb::has_hotplug() {
+            return Ok(None);
+        }
+
+        // Not filtered by id since the quirk table may list several.
+        let registration = HotplugBuilder::new()
+            .enumerate(true)
+            .register(&bus.context, Box::new(HotplugHandler { sender }))?;
+
+        Ok(Some(Box::new(UsbWatch {
+            context: bus.context.clone(),
+            _registration: registration,
+        })))
+    }
+
+    fn open(bus: &UsbBus<T>, id: &AdapterId) -> Result<Self> {
+        let device = Self::adapter_devices(&bus.context)?
+            .into_iter()
+            .find(|device| Self::adapter_id(device).is_ok_and(|i| i == *id))
+            .ok_or(Error::NoDevice)?;
+
+        let mut handle = device.open()?;
+
+        let family = match bus.quirks_override() {
+            Some(family) => family,
+            None => Self::detect_family(&handle)?,
+        };
+        info!("Using {:?} quirks for adapter {}", family, id);
+        let quirks = family.quirks();
+
+        handle.claim_interface(0)?;
+
+        let endpoints = Self::find_endpoints(&handle, quirks.endpoints)?;
+
+        let transport = Self {
+            handle,
+            endpoints,
+            quirks,
+        };
+        transport.handshake()?;
+
+        Ok(transport)
+    }
+
+    fn read_payload(&self, payload: &mut Payload) -> Result<usize> {
+        Ok(self
+            .handle
+            .read_interrupt(self.endpoints.in_, payload, ALLOWED_TIMEOUT)?)
+    }
+
+    fn validation(&self) -> Validation {
+        self.quirks.validation
+    }
+
+    fn write_rumble(&self, states: [Rumble; Port::COUNT]) -> Result<()> {
+        let payload = codec::encode_rumble(states);
+
+        _ = self
+            .handle
+            .write_interrupt(self.endpoints.out, &payload, ALLOWED_TIMEOUT)?;
+
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<()> {
+        self.write_rumble([Rumble::Off; Port::COUNT])
+    }
+}
@@ -0,0 +1,486 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hasher},
+    io,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::atomic::AtomicCell;
+use enclose::enclose;
+use gcinput::{Input, Rumble};
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+pub use super::trigger::TriggerMode;
+
+use super::Bridge;
+use crate::{adapter::Port, feeder, util::packed_bools};
+use protocol::{PadData, Registration, Request, SlotState, SLOT_COUNT};
+
+pub mod protocol;
+
+pub const DEFAULT_PORT: u16 = 26760;
+
+/// Subscriptions lapse when a client stops asking for pad data for this long.
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the server thread checks whether it should stop.
+const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Servers shared by the bridges of every port, keyed by the address they are bound to.
+static SERVERS: Lazy<Mutex<HashMap<SocketAddr, Weak<Server>>>> = Lazy::new(Default::default);
+
+/// A DSU server with one slot for each adapter port.
+pub struct Server {
+    context: Arc<Context>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Server {
+    pub fn bind(address: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_read_timeout(Some(RECV_TIMEOUT))?;
+
+        let context = Arc::new(Context {
+            socket,
+            id: RandomState::new().build_hasher().finish() as u32,
+            start: Instant::now(),
+            stop_flag: Default::default(),
+            state: Default::default(),
+        });
+        let thread = Some(thread::spawn(
+            enclose!((context) move || context.recv_loop()),
+        ));
+
+        Ok(Self { context, thread })
+    }
+
+    /// Returns the server bound to `address`, binding one if no bridge is using it.
+    pub fn shared(address: SocketAddr) -> io::Result<Arc<Self>> {
+        let mut servers = SERVERS.lock().unwrap();
+
+        if let Some(server) = servers.get(&address).and_then(Weak::upgrade) {
+            return Ok(server);
+        }
+
+        let server = Arc::new(Self::bind(address)?);
+        servers.insert(address, Arc::downgrade(&server));
+
+        Ok(server)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.context.socket.local_addr()
+    }
+
+    /// Reserves a slot for one bridge, failing if another bridge already feeds it.
+    fn claim(&self, slot: usize) -> io::Result<()> {
+        let mut state = self.context.state.lock().unwrap();
+
+        if state.claimed[slot] {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("DSU slot {} is already fed by another controller", slot + 1),
+            ));
+        }
+
+        state.claimed[slot] = true;
+        Ok(())
+    }
+
+    /// Empties a slot and frees it for another bridge.
+    fn release(&self, slot: usize) {
+        self.update(slot, None);
+        self.context.state.lock().unwrap().claimed[slot] = false;
+    }
+
+    /// Sets a slot's controller and sends it to the slot's subscribers.
+    ///
+    /// Clients that cannot be sent to are dropped rather than failing the feed.
+    pub fn update(&self, slot: usize, pad: Option<PadData>) {
+        let mut state = self.context.state.lock().unwrap();
+        state.slots[slot] = pad;
+
+        state.clients.retain(|&address, client| {
+            if client.is_expired() {
+                return false;
+            }
+
+            if client.is_subscribed(slot) {
+                if let Err(e) = self
+                    .context
+                    .send_pad_data(address, client, slot, pad.as_ref())
+                {
+                    warn!("Dropping DSU client {}: {}", address, e);
+                    return false;
+                }
+            }
+
+            true
+        });
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.context.stop_flag.store(true, Ordering::Release);
+
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+struct Context {
+    socket: UdpSocket,
+    id: u32,
+    start: Instant,
+    stop_flag: AtomicBool,
+    state: Mutex<State>,
+}
+
+impl Context {
+    fn recv_loop(&self) {
+        let mut buf = [0_u8; 1024];
+
+        while !self.stop_flag.load(Ordering::Acquire) {
+            let (len, address) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    continue
+                }
+                Err(e) => {
+                    // Windows reports clients that went away as reset connections.
+                    debug!("DSU receive error: {}", e);
+                    continue;
+                }
+            };
+
+            let request = match protocol::decode_request(&buf[..len]) {
+                Ok((_, request)) => request,
+                Err(e) => {
+                    debug!("Ignoring DSU packet from {}: {}", address, e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = self.respond(address, request) {
+                warn!("Failed to respond to DSU client {}: {}", address, e);
+            }
+        }
+    }
+
+    fn respond(&self, address: SocketAddr, request: Request) -> io::Result<()> {
+        match request {
+            Request::Version => {
+                self.socket
+                    .send_to(&protocol::encode_version(self.id), address)?;
+            }
+            Request::PortInfo { slots } => {
+                let state = self.state.lock().unwrap();
+
+                for slot in slots.into_iter().filter(|&s| usize::from(s) < SLOT_COUNT) {
+                    let slot_state = if state.slots[usize::from(slot)].is_some() {
+                        SlotState::Connected
+                    } else {
+                        SlotState::Disconnected
+                    };
+
+                    self.socket.send_to(
+                        &protocol::encode_port_info(self.id, slot, slot_state),
+                        address,
+                    )?;
+                }
+            }
+            Request::PadData(registration) => {
+                let mut state = self.state.lock().unwrap();
+                let State { slots, clients, .. } = &mut *state;
+                let client = clients.entry(address).or_default();
+                client.subscribe(registration);
+
+                // Send the current state right away rather than on the next feed.
+                for (slot, pad) in slots.iter().enumerate() {
+                    if pad.is_some() && registration.matches(slot as u8) {
+                        self.send_pad_data(address, client, slot, pad.as_ref())?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn send_pad_data(
+        &self,
+        address: SocketAddr,
+        client: &mut Client,
+        slot: usize,
+        pad: Option<&PadData>,
+    ) -> io::Result<()> {
+        let packet_number = &mut client.packet_numbers[slot];
+        let packet = protocol::encode_pad_data(
+            self.id,
+            slot as u8,
+            pad,
+            *packet_number,
+            self.start.elapsed().as_micros() as u64,
+        );
+        *packet_number = packet_number.wrapping_add(1);
+
+        self.socket.send_to(&packet, address).map(|_| ())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    slots: [Option<PadData>; SLOT_COUNT],
+    /// Which slots a bridge is feeding.
+    claimed: [bool; SLOT_COUNT],
+    clients: HashMap<SocketAddr, Client>,
+}
+
+#[derive(Default)]
+struct Client {
+    /// When each slot was last asked for.
+    subscribed: [Option<Instant>; SLOT_COUNT],
+    packet_numbers: [u32; SLOT_COUNT],
+}
+
+impl Client {
+    fn subscribe(&mut self, registration: Registration) {
+        let now = Instant::now();
+
+        for (slot, subscribed) in self.subscribed.iter_mut().enumerate() {
+            if registration.matches(slot as u8) {
+                *subscribed = Some(now);
+            }
+        }
+    }
+
+    fn is_subscribed(&self, slot: usize) -> bool {
+        self.subscribed[slot].is_some_and(|t| t.elapsed() < CLIENT_TIMEOUT)
+    }
+
+    fn is_expired(&self) -> bool {
+        (0..SLOT_COUNT).all(|slot| !self.is_subscribed(slot))
+    }
+}
+
+/// Feeds one adapter port into the matching slot of a shared DSU server.
+pub struct DsuBridge {
+    config: AtomicCell<Config>,
+    server: Arc<Server>,
+    slot: usize,
+}
+
+impl DsuBridge {
+    pub fn new(config: Config, port: Port) -> io::Result<Self> {
+        Self::with_server(config, Server::shared(config.address)?, port)
+    }
+
+    /// Feeds `port`'s slot of `server`, failing if another bridge already does.
+    pub fn with_server(config: Config, server: Arc<Server>, port: Port) -> io::Result<Self> {
+        server.claim(port.index())?;
+
+        Ok(Self {
+            config: AtomicCell::new(config),
+            server,
+            slot: port.index(),
+        })
+    }
+
+    pub fn pad_data(&self, input: &Input) -> PadData {
+        let triggers = self.config.load().trigger_mode.apply(input);
+        let analog = |pressed: bool| if pressed { u8::MAX } else { u8::MIN };
+        let l2 = triggers.l == u8::MAX;
+        let r2 = triggers.r == u8::MAX;
+
+        PadData {
+            buttons1: packed_bools!((u8)
+                false,
+                triggers.ls,
+                triggers.rs,
+                input.button_start,
+                input.button_up,
+                input.button_right,
+                input.button_down,
+                input.button_left,
+            ),
+            buttons2: packed_bools!((u8)
+                l2,
+                r2,
+                false,
+                input.button_z,
+                input.button_x,
+                input.button_a,
+                input.button_b,
+                input.button_y,
+            ),
+            left_stick: input.main_stick,
+            right_stick: input.c_stick,
+            analog_dpad: [
+                analog(input.button_left),
+                analog(input.button_down),
+                analog(input.button_right),
+                analog(input.button_up),
+            ],
+            analog_face: [
+                analog(input.button_y),
+                analog(input.button_b),
+                analog(input.button_a),
+                analog(input.button_x),
+            ],
+            analog_shoulders: [analog(input.button_z), 0, triggers.r, triggers.l],
+        }
+    }
+}
+
+impl Bridge for DsuBridge {
+    fn driver_name(&self) -> &'static str {
+        "DSU"
+    }
+
+    fn feed(&self, input: &Option<Input>) -> super::Result<()> {
+        let pad = input.as_ref().map(|input| self.pad_data(input));
+        self.server.update(self.slot, pad);
+
+        Ok(())
+    }
+
+    fn rumble_state(&self) -> Rumble {
+        Rumble::Off
+    }
+
+    fn notify_rumble_consumed(&self) {}
+
+    fn reconfigure(&self, config: &feeder::Config) -> bool {
+        // Another address needs another server.
+        if config.dsu_config.address != self.config.load().address {
+            return false;
+        }
+
+        self.config.store(config.dsu_config);
+        true
+    }
+}
+
+impl Drop for DsuBridge {
+    fn drop(&mut self) {
+        self.server.release(self.slot);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    pub address: SocketAddr,
+    pub trigger_mode: TriggerMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            address: (Ipv4Addr::LOCALHOST, DEFAULT_PORT).into(),
+            trigger_mode: TriggerMode::Combination,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        net::{Ipv4Addr, UdpSocket},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use gcinput::{Input, Stick};
+
+    use super::{
+        protocol::{self, CLIENT_MAGIC, PAD_DATA_MESSAGE, PORT_INFO_MESSAGE, SERVER_MAGIC},
+        Config, DsuBridge, Server,
+    };
+    use crate::{adapter::Port, bridge::Bridge};
+
+    fn recv(client: &UdpSocket) -> (u32, Vec<u8>) {
+        let mut buf = [0_u8; 128];
+        let len = client.recv(&mut buf).unwrap();
+        let (_, message_type, body) = protocol::decode(&buf[..len], SERVER_MAGIC).unwrap();
+        (message_type, body.to_vec())
+    }
+
+    #[test]
+    fn dsu_server_serves_subscribed_clients() {
+        let server = Arc::new(Server::bind((Ipv4Addr::LOCALHOST, 0).into()).unwrap());
+        let bridge = DsuBridge::with_server(Config::default(), server.clone(), Port::Two).unwrap();
+
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        client.connect(server.local_addr().unwrap()).unwrap();
+
+        let input = Input {
+            button_a: true,
+            button_r: true,
+            main_stick: Stick::new(0x20, 0xE0),
+            ..Default::default()
+        };
+        bridge.feed(&Some(input)).unwrap();
+
+        let request = protocol::encode(CLIENT_MAGIC, 1, PORT_INFO_MESSAGE, &[2, 0, 0, 0, 0, 1]);
+        client.send(&request).unwrap();
+        assert_eq!(recv(&client), (PORT_INFO_MESSAGE, vec![0; 12]));
+        let (_, body) = recv(&client);
+        assert_eq!(&body[..2], &[1, 2]);
+
+        // Subscribe to the second slot by slot number.
+        let request =
+            protocol::encode(CLIENT_MAGIC, 1, PAD_DATA_MESSAGE, &[1, 1, 0, 0, 0, 0, 0, 0]);
+        client.send(&request).unwrap();
+        let (message_type, body) = recv(&client);
+        assert_eq!(message_type, PAD_DATA_MESSAGE);
+        assert_eq!(body[0], 1);
+        // A and R2 are held, and the main stick is the left stick.
+        assert_eq!(body[17], 0x22);
+        assert_eq!(&body[20..22], &[0x20, 0xE0]);
+
+        bridge.feed(&None).unwrap();
+        let (_, body) = recv(&client);
+        assert_eq!(body[11], 0);
+        assert_eq!(u32::from_le_bytes(body[12..16].try_into().unwrap()), 1);
+    }
+
+    #[test]
+    fn one_bridge_feeds_each_slot() {
+        let server = Arc::new(Server::bind((Ipv4Addr::LOCALHOST, 0).into()).unwrap());
+        let first = DsuBridge::with_server(Config::default(), server.clone(), Port::One).unwrap();
+
+        // Another adapter's controller on the same port would overwrite the first.
+        let error = DsuBridge::with_server(Config::default(), server.clone(), Port::One)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        assert!(DsuBridge::with_server(Config::default(), server.clone(), Port::Two).is_ok());
+
+        first.feed(&Some(Input::default())).unwrap();
+        drop(first);
+        assert!(server.context.state.lock().unwrap().slots[0].is_none());
+
+        let second = DsuBridge::with_server(Config::default(), server.clone(), Port::One).unwrap();
+        second.feed(&Some(Input::default())).unwrap();
+        assert!(server.context.state.lock().unwrap().slots[0].is_some());
+    }
+}
@@ -0,0 +1,318 @@
+//! Packets of the cemuhook DSU protocol.
+//!
+//! Every packet starts with a 16 byte header: a magic, the protocol version, the
+//! length of the packet after the header, a CRC32 of the whole packet taken with
+//! the checksum zeroed and the sender's id. The message type follows the header.
+
+use gcinput::Stick;
+
+pub const PROTOCOL_VERSION: u16 = 1001;
+pub const HEADER_LEN: usize = 16;
+pub const SLOT_COUNT: usize = 4;
+
+pub const CLIENT_MAGIC: [u8; 4] = *b"DSUC";
+pub const SERVER_MAGIC: [u8; 4] = *b"DSUS";
+
+pub const VERSION_MESSAGE: u32 = 0x10_0000;
+pub const PORT_INFO_MESSAGE: u32 = 0x10_0001;
+pub const PAD_DATA_MESSAGE: u32 = 0x10_0002;
+
+/// Largest number of slots a client may ask about in one port info request.
+const MAX_PORT_INFO_SLOTS: usize = 4;
+
+const CHECKSUM_RANGE: std::ops::Range<usize> = 8..12;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PacketError {
+    #[error("packet is too short")]
+    TooShort,
+    #[error("unexpected magic {0:?}")]
+    Magic([u8; 4]),
+    #[error("unsupported protocol version {0}")]
+    Version(u16),
+    #[error("checksum mismatch")]
+    Checksum,
+    #[error("unknown message type {0:#x}")]
+    MessageType(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    Version,
+    PortInfo { slots: Vec<u8> },
+    PadData(Registration),
+}
+
+/// Which slots a pad data request subscribes to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Registration {
+    pub slot: Option<u8>,
+    pub mac: Option<[u8; 6]>,
+}
+
+impl Registration {
+    const SLOT_BIT: u8 = 1 << 0;
+    const MAC_BIT: u8 = 1 << 1;
+
+    /// A registration without a slot or MAC address subscribes to every slot.
+    pub fn matches(&self, slot: u8) -> bool {
+        match (self.slot, self.mac) {
+            (None, None) => true,
+            (s, m) => s == Some(slot) || m == Some(mac_address(slot)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SlotState {
+    Disconnected = 0,
+    Connected = 2,
+}
+
+/// Controller state as sent in a pad data response.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct PadData {
+    /// D-Pad left, down, right, up, options, R3, L3 and share from the high bit down.
+    pub buttons1: u8,
+    /// Y, B, A, X, R1, L1, R2 and L2 from the high bit down.
+    pub buttons2: u8,
+    pub left_stick: Stick,
+    pub right_stick: Stick,
+    /// D-Pad left, down, right and up.
+    pub analog_dpad: [u8; 4],
+    /// Y, B, A and X.
+    pub analog_face: [u8; 4],
+    /// R1, L1, R2 and L2.
+    pub analog_shoulders: [u8; 4],
+}
+
+/// The MAC address reported for a slot, which clients may subscribe by.
+pub const fn mac_address(slot: u8) -> [u8; 6] {
+    [0x00, 0x00, 0x00, 0x00, 0x00, slot + 1]
+}
+
+pub fn decode_request(packet: &[u8]) -> Result<(u32, Request), PacketError> {
+    let (client_id, message_type, body) = decode(packet, CLIENT_MAGIC)?;
+
+    let request = match message_type {
+        VERSION_MESSAGE => Request::Version,
+        PORT_INFO_MESSAGE => {
+            let count = body.get(..4).ok_or(PacketError::TooShort)?;
+            let count = i32::from_le_bytes(count.try_into().unwrap());
+            let count = usize::try_from(count).unwrap_or(0).min(MAX_PORT_INFO_SLOTS);
+            let slots = body.get(4..4 + count).ok_or(PacketError::TooShort)?;
+
+            Request::PortInfo {
+                slots: slots.to_vec(),
+            }
+        }
+        PAD_DATA_MESSAGE => {
+            let body = body.get(..8).ok_or(PacketError::TooShort)?;
+            let flags = body[0];
+
+            Request::PadData(Registration {
+                slot: (flags & Registration::SLOT_BIT != 0).then_some(body[1]),
+                mac: (flags & Registration::MAC_BIT != 0).then(|| body[2..8].try_into().unwrap()),
+            })
+        }
+        other => return Err(PacketError::MessageType(other)),
+    };
+
+    Ok((client_id, request))
+}
+
+pub fn encode_version(server_id: u32) -> Vec<u8> {
+    encode(
+        SERVER_MAGIC,
+        server_id,
+        VERSION_MESSAGE,
+        &PROTOCOL_VERSION.to_le_bytes(),
+    )
+}
+
+pub fn encode_port_info(server_id: u32, slot: u8, state: SlotState) -> Vec<u8> {
+    let mut body = slot_header(slot, state).to_vec();
+    body.push(0);
+
+    encode(SERVER_MAGIC, server_id, PORT_INFO_MESSAGE, &body)
+}
+
+pub fn encode_pad_data(
+    server_id: u32,
+    slot: u8,
+    pad: Option<&PadData>,
+    packet_number: u32,
+    timestamp_micros: u64,
+) -> Vec<u8> {
+    let state = if pad.is_some() {
+        SlotState::Connected
+    } else {
+        SlotState::Disconnected
+    };
+    let pad = pad.copied().unwrap_or_default();
+
+    let mut body = slot_header(slot, state).to_vec();
+    body.push(u8::from(state == SlotState::Connected));
+    body.extend_from_slice(&packet_number.to_le_bytes());
+    body.extend_from_slice(&[pad.buttons1, pad.buttons2]);
+    // The home and touch buttons.
+    body.extend_from_slice(&[0, 0]);
+    body.extend_from_slice(&[
+        pad.left_stick.x,
+        pad.left_stick.y,
+        pad.right_stick.x,
+        pad.right_stick.y,
+    ]);
+    body.extend_from_slice(&pad.analog_dpad);
+    body.extend_from_slice(&pad.analog_face);
+    body.extend_from_slice(&pad.analog_shoulders);
+    // Two inactive touches.
+    body.extend_from_slice(&[0; 12]);
+    body.extend_from_slice(&timestamp_micros.to_le_bytes());
+    // Accelerometer and gyroscope readings.
+    body.extend_from_slice(&[0; 24]);
+
+    encode(SERVER_MAGIC, server_id, PAD_DATA_MESSAGE, &body)
+}
+
+/// The slot description shared by port info and pad data responses.
+fn slot_header(slot: u8, state: SlotState) -> [u8; 11] {
+    let connected = state == SlotState::Connected;
+    let mac = if connected { mac_address(slot) } else { [0; 6] };
+
+    let mut header = [0_u8; 11];
+    header[0] = slot;
+    header[1] = state as u8;
+    // No gyro, connected by USB and a full battery.
+    header[2] = u8::from(connected);
+    header[3] = u8::from(connected);
+    header[4..10].copy_from_slice(&mac);
+    header[10] = if connected { 0x05 } else { 0x00 };
+
+    header
+}
+
+pub(super) fn encode(magic: [u8; 4], sender_id: u32, message_type: u32, body: &[u8]) -> Vec<u8> {
+    let len = u16::try_from(4 + body.len()).expect("DSU packets fit in a u16 length");
+
+    let mut packet = Vec::with_capacity(HEADER_LEN + usize::from(len));
+    packet.extend_from_slice(&magic);
+    packet.extend_from_slice(&PROTOCOL_VERSION.to_le_bytes());
+    packet.extend_from_slice(&len.to_le_bytes());
+    packet.extend_from_slice(&[0; 4]);
+    packet.extend_from_slice(&sender_id.to_le_bytes());
+    packet.extend_from_slice(&message_type.to_le_bytes());
+    packet.extend_from_slice(body);
+
+    let checksum = crc32fast::hash(&packet);
+    packet[CHECKSUM_RANGE].copy_from_slice(&checksum.to_le_bytes());
+
+    packet
+}
+
+/// Checks a packet's header and returns the sender's id, the message type and the
+/// rest of the packet.
+pub(super) fn decode(packet: &[u8], magic: [u8; 4]) -> Result<(u32, u32, &[u8]), PacketError> {
+    let word = |range: std::ops::Range<usize>| -> [u8; 4] { packet[range].try_into().unwrap() };
+
+    if packet.len() < HEADER_LEN + 4 {
+        return Err(PacketError::TooShort);
+    }
+
+    if word(0..4) != magic {
+        return Err(PacketError::Magic(word(0..4)));
+    }
+
+    let version = u16::from_le_bytes([packet[4], packet[5]]);
+    if version != PROTOCOL_VERSION {
+        return Err(PacketError::Version(version));
+    }
+
+    // Longer packets are truncated to their stated length.
+    let len = HEADER_LEN + usize::from(u16::from_le_bytes([packet[6], packet[7]]));
+    let packet = packet.get(..len).ok_or(PacketError::TooShort)?;
+
+    let checksum = u32::from_le_bytes(word(CHECKSUM_RANGE));
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&packet[..CHECKSUM_RANGE.start]);
+    hasher.update(&[0; 4]);
+    hasher.update(&packet[CHECKSUM_RANGE.end..]);
+    if hasher.finalize() != checksum {
+        return Err(PacketError::Checksum);
+    }
+
+    let sender_id = u32::from_le_bytes(word(12..16));
+    let message_type = u32::from_le_bytes(word(16..20));
+
+    Ok((sender_id, message_type, &packet[HEADER_LEN + 4..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_packet(message_type: u32, body: &[u8]) -> Vec<u8> {
+        encode(CLIENT_MAGIC, 0xC0FFEE, message_type, body)
+    }
+
+    #[test]
+    fn requests_decode() {
+        assert_eq!(
+            decode_request(&client_packet(VERSION_MESSAGE, &[])),
+            Ok((0xC0FFEE, Request::Version))
+        );
+        assert_eq!(
+            decode_request(&client_packet(PORT_INFO_MESSAGE, &[2, 0, 0, 0, 1, 3])),
+            Ok((0xC0FFEE, Request::PortInfo { slots: vec![1, 3] }))
+        );
+        assert_eq!(
+            decode_request(&client_packet(PAD_DATA_MESSAGE, &[1, 2, 0, 0, 0, 0, 0, 0])),
+            Ok((
+                0xC0FFEE,
+                Request::PadData(Registration {
+                    slot: Some(2),
+                    mac: None
+                })
+            ))
+        );
+
+        let mut corrupt = client_packet(VERSION_MESSAGE, &[]);
+        corrupt[15] ^= 1;
+        assert_eq!(decode_request(&corrupt), Err(PacketError::Checksum));
+        assert_eq!(
+            decode_request(&encode_version(1)),
+            Err(PacketError::Magic(SERVER_MAGIC))
+        );
+    }
+
+    #[test]
+    fn pad_data_has_expected_layout() {
+        let pad = PadData {
+            buttons2: 0x20,
+            left_stick: Stick::new(0x12, 0x34),
+            ..Default::default()
+        };
+        let packet = encode_pad_data(7, 1, Some(&pad), 42, 1000);
+        let (server_id, message_type, body) = decode(&packet, SERVER_MAGIC).unwrap();
+
+        assert_eq!(packet.len(), 100);
+        assert_eq!((server_id, message_type), (7, PAD_DATA_MESSAGE));
+        assert_eq!(&body[..2], &[1, SlotState::Connected as u8]);
+        assert_eq!(body[11], 1);
+        assert_eq!(u32::from_le_bytes(body[12..16].try_into().unwrap()), 42);
+        assert_eq!(body[17], 0x20);
+        assert_eq!(&body[20..22], &[0x12, 0x34]);
+
+        assert!(Registration::default().matches(3));
+        assert!(Registration {
+            slot: None,
+            mac: Some(mac_address(1)),
+        }
+        .matches(1));
+        assert!(!Registration {
+            slot: Some(0),
+            mac: None,
+        }
+        .matches(1));
+    }
+}
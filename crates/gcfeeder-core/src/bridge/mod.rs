@@ -1,6 +1,13 @@
 use gcinput::{Input, Rumble};
 
+use crate::feeder;
+
+pub mod dsu;
+pub mod null;
+pub mod pipe;
 pub mod rumble;
+pub mod trigger;
+pub mod uinput;
 pub mod vigem;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -10,10 +17,22 @@ pub trait Bridge {
     fn feed(&self, input: &Option<Input>) -> Result<()>;
     fn rumble_state(&self) -> Rumble;
     fn notify_rumble_consumed(&self);
+    /// Applies this bridge's options from `config` in place, returning `false`
+    /// if they only take effect on a new connection.
+    fn reconfigure(&self, config: &feeder::Config) -> bool;
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    #[cfg(windows)]
     #[error("vigem: {0}")]
     ViGEm(#[from] vigem_client::Error),
+    #[error("dsu: {0}")]
+    Dsu(std::io::Error),
+    #[error("pipe: {0}")]
+    Pipe(std::io::Error),
+    #[error("uinput: {0}")]
+    Uinput(#[from] std::io::Error),
+    #[error("{0} is not supported on this platform")]
+    Unsupported(&'static str),
 }
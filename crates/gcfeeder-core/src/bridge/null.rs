@@ -0,0 +1,92 @@
+use std::sync::{Arc, Mutex};
+
+use gcinput::{Input, Rumble};
+
+use super::Bridge;
+use crate::feeder;
+
+/// Accepts every input without sending it anywhere, for running a feeder only
+/// for its callbacks.
+#[derive(Debug, Clone, Default)]
+pub struct NullBridge {
+    recorded: Option<Arc<Mutex<Vec<Option<Input>>>>>,
+}
+
+impl NullBridge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A bridge that keeps every input fed to it. Clones share the recording.
+    pub fn recording() -> Self {
+        Self {
+            recorded: Some(Default::default()),
+        }
+    }
+
+    /// The inputs fed so far, which is always empty unless recording.
+    #[must_use]
+    pub fn recorded(&self) -> Vec<Option<Input>> {
+        self.recorded
+            .as_ref()
+            .map(|r| r.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    pub fn take_recorded(&self) -> Vec<Option<Input>> {
+        self.recorded
+            .as_ref()
+            .map(|r| std::mem::take(&mut *r.lock().unwrap()))
+            .unwrap_or_default()
+    }
+}
+
+impl Bridge for NullBridge {
+    fn driver_name(&self) -> &'static str {
+        "None"
+    }
+
+    fn feed(&self, input: &Option<Input>) -> super::Result<()> {
+        if let Some(recorded) = self.recorded.as_ref() {
+            recorded.lock().unwrap().push(*input);
+        }
+
+        Ok(())
+    }
+
+    fn rumble_state(&self) -> Rumble {
+        Rumble::Off
+    }
+
+    fn notify_rumble_consumed(&self) {}
+
+    fn reconfigure(&self, _config: &feeder::Config) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gcinput::Input;
+
+    use super::NullBridge;
+    use crate::bridge::Bridge;
+
+    #[test]
+    fn recording_is_shared_between_clones() {
+        let bridge = NullBridge::recording();
+        let input = Some(Input {
+            button_a: true,
+            ..Default::default()
+        });
+
+        bridge.clone().feed(&input).unwrap();
+        bridge.feed(&None).unwrap();
+        assert_eq!(bridge.take_recorded(), vec![input, None]);
+        assert!(bridge.recorded().is_empty());
+
+        let null = NullBridge::new();
+        null.feed(&input).unwrap();
+        assert!(null.recorded().is_empty());
+    }
+}
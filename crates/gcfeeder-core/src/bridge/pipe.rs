@@ -0,0 +1,260 @@
+//! Feeds Dolphin's pipe input device, which reads text commands from a FIFO in
+//! the `Pipes` folder of Dolphin's user directory.
+
+use std::{env, fmt::Write, path::PathBuf};
+
+use gcinput::Input;
+
+#[cfg(unix)]
+pub use unix::PipeBridge;
+
+use crate::adapter::Port;
+
+/// The path of the pipe fed for `port` in Dolphin's default user directory.
+pub fn default_path(port: Port) -> Option<PathBuf> {
+    Some(
+        user_dir()?
+            .join("Pipes")
+            .join(format!("gcfeeder{}", port.index() + 1)),
+    )
+}
+
+fn user_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("DOLPHIN_EMU_USERPATH") {
+        return Some(dir.into());
+    }
+
+    let home = PathBuf::from(env::var_os("HOME")?);
+
+    if cfg!(target_os = "macos") {
+        return Some(home.join("Library/Application Support/Dolphin"));
+    }
+
+    // Dolphin keeps using the old location if it exists.
+    let legacy = home.join(".dolphin-emu");
+    if legacy.is_dir() {
+        return Some(legacy);
+    }
+
+    let data = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local/share"));
+
+    Some(data.join("dolphin-emu"))
+}
+
+/// Builds the commands that take Dolphin from `sent` to `input`, or from an
+/// unknown state if nothing has been sent.
+pub fn commands(sent: Option<&Input>, input: &Input) -> String {
+    let buttons = |i: &Input| {
+        [
+            ("A", i.button_a),
+            ("B", i.button_b),
+            ("X", i.button_x),
+            ("Y", i.button_y),
+            ("Z", i.button_z),
+            ("START", i.button_start),
+            ("L", i.button_l),
+            ("R", i.button_r),
+            ("D_UP", i.button_up),
+            ("D_DOWN", i.button_down),
+            ("D_LEFT", i.button_left),
+            ("D_RIGHT", i.button_right),
+        ]
+    };
+    let axis = |value: u8| f64::from(value) / f64::from(u8::MAX);
+
+    let mut commands = String::new();
+
+    let previous = sent.map(buttons);
+    for (i, (name, pressed)) in buttons(input).into_iter().enumerate() {
+        if previous.map(|p| p[i].1) != Some(pressed) {
+            let action = if pressed { "PRESS" } else { "RELEASE" };
+            let _ = writeln!(commands, "{} {}", action, name);
+        }
+    }
+
+    for (name, stick, previous) in [
+        ("MAIN", input.main_stick, sent.map(|s| s.main_stick)),
+        ("C", input.c_stick, sent.map(|s| s.c_stick)),
+    ] {
+        if previous != Some(stick) {
+            let _ = writeln!(
+                commands,
+                "SET {} {:.4} {:.4}",
+                name,
+                axis(stick.x),
+                axis(stick.y)
+            );
+        }
+    }
+
+    for (name, value, previous) in [
+        ("L", input.left_trigger, sent.map(|s| s.left_trigger)),
+        ("R", input.right_trigger, sent.map(|s| s.right_trigger)),
+    ] {
+        if previous != Some(value) {
+            let _ = writeln!(commands, "SET {} {:.4}", name, axis(value));
+        }
+    }
+
+    commands
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        ffi::CString,
+        fs::{File, OpenOptions},
+        io::{self, Write},
+        os::unix::{ffi::OsStrExt, fs::OpenOptionsExt},
+        path::Path,
+        sync::Mutex,
+    };
+
+    use gcinput::{Input, Rumble};
+
+    use super::{commands, default_path};
+    use crate::{
+        adapter::Port,
+        bridge::{self, Bridge},
+        feeder,
+    };
+
+    /// Writes inputs to a Dolphin pipe, sending only what changed since the last feed.
+    pub struct PipeBridge {
+        state: Mutex<State>,
+    }
+
+    struct State {
+        pipe: File,
+        /// What Dolphin was last sent, if it is known.
+        sent: Option<Input>,
+    }
+
+    impl PipeBridge {
+        pub fn new(port: Port) -> io::Result<Self> {
+            let path = default_path(port).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "Dolphin user directory not found")
+            })?;
+
+            Self::open(path)
+        }
+
+        /// Opens the pipe at `path`, creating it if needed. Fails unless something
+        /// is reading from the pipe.
+        pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+            let path = path.as_ref();
+
+            if !path.exists() {
+                let c_path = CString::new(path.as_os_str().as_bytes())?;
+                // SAFETY: `c_path` is a valid nul-terminated string.
+                if unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) } != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+
+            let pipe = OpenOptions::new()
+                .write(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(path)?;
+
+            Ok(Self {
+                state: Mutex::new(State { pipe, sent: None }),
+            })
+        }
+    }
+
+    impl Bridge for PipeBridge {
+        fn driver_name(&self) -> &'static str {
+            "Dolphin pipe"
+        }
+
+        fn feed(&self, input: &Option<Input>) -> bridge::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            // Dolphin has no notion of a disconnected pad, so leave it neutral.
+            let input = input.unwrap_or_default();
+            let commands = commands(state.sent.as_ref(), &input);
+
+            if commands.is_empty() {
+                return Ok(());
+            }
+
+            // Writes this small are atomic, so either every command is sent or none are.
+            match state.pipe.write(commands.as_bytes()) {
+                Ok(_) => state.sent = Some(input),
+                // Dolphin is not keeping up, resend everything once it does.
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => state.sent = None,
+                Err(e) => return Err(bridge::Error::Pipe(e)),
+            }
+
+            Ok(())
+        }
+
+        fn rumble_state(&self) -> Rumble {
+            Rumble::Off
+        }
+
+        fn notify_rumble_consumed(&self) {}
+
+        fn reconfigure(&self, _config: &feeder::Config) -> bool {
+            true
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::{
+            fs::{self, OpenOptions},
+            io::Read,
+            os::unix::fs::OpenOptionsExt,
+            process,
+        };
+
+        use gcinput::{Input, Stick};
+
+        use super::PipeBridge;
+        use crate::bridge::Bridge;
+
+        #[test]
+        fn pipe_bridge_writes_diffs() {
+            let dir = std::env::temp_dir().join(format!("gcfeeder-pipe-{}", process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("gcfeeder1");
+
+            assert!(PipeBridge::open(&path).is_err());
+
+            let mut reader = OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(&path)
+                .unwrap();
+            let bridge = PipeBridge::open(&path).unwrap();
+            let mut read = || {
+                let mut buf = String::new();
+                let _ = reader.read_to_string(&mut buf);
+                buf
+            };
+
+            let mut input = Input {
+                button_a: true,
+                ..Default::default()
+            };
+            bridge.feed(&Some(input)).unwrap();
+            let commands = read();
+            assert!(commands.starts_with("PRESS A\nRELEASE B\n"));
+            assert!(commands.ends_with("SET L 0.0000\nSET R 0.0000\n"));
+
+            bridge.feed(&Some(input)).unwrap();
+            assert_eq!(read(), "");
+
+            input.button_a = false;
+            input.main_stick = Stick::new(0xFF, 0x00);
+            input.right_trigger = 0xFF;
+            bridge.feed(&Some(input)).unwrap();
+            assert_eq!(read(), "RELEASE A\nSET MAIN 1.0000 0.0000\nSET R 1.0000\n");
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
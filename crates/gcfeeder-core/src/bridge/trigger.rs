@@ -0,0 +1,61 @@
+use enum_iterator::Sequence;
+use gcinput::Input;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Sequence)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerMode {
+    Analog,
+    Digital,
+    Combination,
+    StickClick,
+}
+
+impl TriggerMode {
+    pub const fn apply(self, input: &Input) -> Triggers {
+        let l: u8;
+        let r: u8;
+        let mut ls: bool = false;
+        let mut rs: bool = false;
+
+        let Input {
+            left_trigger,
+            right_trigger,
+            button_l,
+            button_r,
+            ..
+        } = *input;
+
+        match self {
+            Self::Analog => {
+                l = left_trigger;
+                r = right_trigger;
+            }
+            Self::Digital => {
+                l = if button_l { u8::MAX } else { u8::MIN };
+                r = if button_r { u8::MAX } else { u8::MIN };
+            }
+            Self::Combination => {
+                l = if button_l { u8::MAX } else { left_trigger };
+                r = if button_r { u8::MAX } else { right_trigger };
+            }
+            Self::StickClick => {
+                l = left_trigger;
+                r = right_trigger;
+                ls = button_l;
+                rs = button_r;
+            }
+        }
+
+        Triggers { l, r, ls, rs }
+    }
+}
+
+/// Trigger values along with the stick clicks the trigger buttons map to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Triggers {
+    pub l: u8,
+    pub r: u8,
+    pub ls: bool,
+    pub rs: bool,
+}
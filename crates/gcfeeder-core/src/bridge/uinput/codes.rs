@@ -0,0 +1,36 @@
+//! The evdev event types and codes used by the uinput bridge, from `linux/input-event-codes.h`.
+
+pub const EV_SYN: u16 = 0x00;
+pub const EV_KEY: u16 = 0x01;
+pub const EV_ABS: u16 = 0x03;
+pub const EV_FF: u16 = 0x15;
+pub const EV_UINPUT: u16 = 0x0101;
+
+pub const SYN_REPORT: u16 = 0;
+
+pub const BTN_A: u16 = 0x130;
+pub const BTN_B: u16 = 0x131;
+pub const BTN_X: u16 = 0x133;
+pub const BTN_Y: u16 = 0x134;
+pub const BTN_TR: u16 = 0x137;
+pub const BTN_START: u16 = 0x13B;
+pub const BTN_THUMBL: u16 = 0x13D;
+pub const BTN_THUMBR: u16 = 0x13E;
+pub const BTN_DPAD_UP: u16 = 0x220;
+pub const BTN_DPAD_DOWN: u16 = 0x221;
+pub const BTN_DPAD_LEFT: u16 = 0x222;
+pub const BTN_DPAD_RIGHT: u16 = 0x223;
+
+pub const ABS_X: u16 = 0x00;
+pub const ABS_Y: u16 = 0x01;
+pub const ABS_Z: u16 = 0x02;
+pub const ABS_RX: u16 = 0x03;
+pub const ABS_RY: u16 = 0x04;
+pub const ABS_RZ: u16 = 0x05;
+
+pub const FF_RUMBLE: u16 = 0x50;
+
+/// Sent with `EV_UINPUT` when a program uploads an effect, with the request id as the value.
+pub const UI_FF_UPLOAD: u16 = 1;
+/// Sent with `EV_UINPUT` when a program erases an effect, with the request id as the value.
+pub const UI_FF_ERASE: u16 = 2;
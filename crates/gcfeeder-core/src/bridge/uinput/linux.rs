@@ -0,0 +1,244 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    mem,
+    os::unix::{fs::OpenOptionsExt, io::AsRawFd},
+    path::PathBuf,
+    slice,
+    time::Duration,
+};
+
+use super::{
+    codes::{EV_ABS, EV_FF, EV_KEY, FF_RUMBLE},
+    Capabilities, Device, Effect, Event, Uinput,
+};
+
+const BUS_VIRTUAL: u16 = 0x06;
+
+const IOC_NONE: u32 = 0;
+const IOC_WRITE: u32 = 1;
+const IOC_READ: u32 = 2;
+
+/// Builds a uinput ioctl request number like the kernel's `_IOC` macro.
+const fn ioc(dir: u32, nr: u32, size: usize) -> libc::c_ulong {
+    ((dir << 30) | ((size as u32) << 16) | ((b'U' as u32) << 8) | nr) as libc::c_ulong
+}
+
+const UI_DEV_CREATE: libc::c_ulong = ioc(IOC_NONE, 1, 0);
+const UI_DEV_DESTROY: libc::c_ulong = ioc(IOC_NONE, 2, 0);
+const UI_DEV_SETUP: libc::c_ulong = ioc(IOC_WRITE, 3, mem::size_of::<libc::uinput_setup>());
+const UI_ABS_SETUP: libc::c_ulong = ioc(IOC_WRITE, 4, mem::size_of::<libc::uinput_abs_setup>());
+const UI_SET_EVBIT: libc::c_ulong = ioc(IOC_WRITE, 100, mem::size_of::<libc::c_int>());
+const UI_SET_KEYBIT: libc::c_ulong = ioc(IOC_WRITE, 101, mem::size_of::<libc::c_int>());
+const UI_SET_ABSBIT: libc::c_ulong = ioc(IOC_WRITE, 103, mem::size_of::<libc::c_int>());
+const UI_SET_FFBIT: libc::c_ulong = ioc(IOC_WRITE, 107, mem::size_of::<libc::c_int>());
+const UI_BEGIN_FF_UPLOAD: libc::c_ulong = ioc(
+    IOC_READ | IOC_WRITE,
+    200,
+    mem::size_of::<libc::uinput_ff_upload>(),
+);
+const UI_END_FF_UPLOAD: libc::c_ulong =
+    ioc(IOC_WRITE, 201, mem::size_of::<libc::uinput_ff_upload>());
+const UI_BEGIN_FF_ERASE: libc::c_ulong = ioc(
+    IOC_READ | IOC_WRITE,
+    202,
+    mem::size_of::<libc::uinput_ff_erase>(),
+);
+const UI_END_FF_ERASE: libc::c_ulong = ioc(IOC_WRITE, 203, mem::size_of::<libc::uinput_ff_erase>());
+
+/// Creates virtual devices through the kernel's uinput module.
+#[derive(Debug, Clone)]
+pub struct DevUinput {
+    path: PathBuf,
+}
+
+impl DevUinput {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Default for DevUinput {
+    fn default() -> Self {
+        Self::new("/dev/uinput")
+    }
+}
+
+impl Uinput for DevUinput {
+    type Device = UinputDevice;
+
+    fn create(&self, capabilities: &Capabilities) -> io::Result<UinputDevice> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&self.path)?;
+        let mut device = UinputDevice {
+            file,
+            created: false,
+        };
+
+        device.ioctl(UI_SET_EVBIT, EV_KEY.into())?;
+        for &key in capabilities.keys.iter() {
+            device.ioctl(UI_SET_KEYBIT, key.into())?;
+        }
+
+        device.ioctl(UI_SET_EVBIT, EV_ABS.into())?;
+        for axis in capabilities.axes.iter() {
+            device.ioctl(UI_SET_ABSBIT, axis.code.into())?;
+
+            let setup = libc::uinput_abs_setup {
+                code: axis.code,
+                absinfo: libc::input_absinfo {
+                    value: axis.min,
+                    minimum: axis.min,
+                    maximum: axis.max,
+                    fuzz: 0,
+                    flat: 0,
+                    resolution: 0,
+                },
+            };
+            device.ioctl(UI_ABS_SETUP, &setup as *const _ as libc::c_ulong)?;
+        }
+
+        if !capabilities.ff_effects.is_empty() {
+            device.ioctl(UI_SET_EVBIT, EV_FF.into())?;
+            for &effect in capabilities.ff_effects.iter() {
+                device.ioctl(UI_SET_FFBIT, effect.into())?;
+            }
+        }
+
+        // SAFETY: `uinput_setup` is plain old data.
+        let mut setup: libc::uinput_setup = unsafe { mem::zeroed() };
+        setup.id.bustype = BUS_VIRTUAL;
+        setup.id.version = 1;
+        setup.ff_effects_max = capabilities.ff_effects_max;
+        // Leave room for the nul terminator.
+        for (c, &b) in setup
+            .name
+            .iter_mut()
+            .zip(capabilities.name.as_bytes())
+            .take(libc::UINPUT_MAX_NAME_SIZE - 1)
+        {
+            *c = b as libc::c_char;
+        }
+
+        device.ioctl(UI_DEV_SETUP, &setup as *const _ as libc::c_ulong)?;
+        device.ioctl(UI_DEV_CREATE, 0)?;
+
+        device.created = true;
+
+        Ok(device)
+    }
+}
+
+pub struct UinputDevice {
+    file: File,
+    created: bool,
+}
+
+impl UinputDevice {
+    fn ioctl(&self, request: libc::c_ulong, arg: libc::c_ulong) -> io::Result<()> {
+        // SAFETY: Every request used here either takes an integer or a pointer to a
+        // live struct of the size encoded in the request.
+        let result = unsafe { libc::ioctl(self.file.as_raw_fd(), request as _, arg) };
+
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Device for UinputDevice {
+    fn write(&mut self, events: &[Event]) -> io::Result<()> {
+        let events = events
+            .iter()
+            .map(|event| libc::input_event {
+                time: libc::timeval {
+                    tv_sec: 0,
+                    tv_usec: 0,
+                },
+                type_: event.kind,
+                code: event.code,
+                value: event.value,
+            })
+            .collect::<Vec<_>>();
+
+        // SAFETY: `input_event` is plain old data without padding.
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                events.as_ptr().cast::<u8>(),
+                events.len() * mem::size_of::<libc::input_event>(),
+            )
+        };
+
+        self.file.write_all(bytes)
+    }
+
+    fn read(&mut self) -> io::Result<Option<Event>> {
+        let mut buf = [0_u8; mem::size_of::<libc::input_event>()];
+
+        match self.file.read(&mut buf) {
+            Ok(n) if n == buf.len() => {
+                // SAFETY: The kernel wrote a whole `input_event`.
+                let event: libc::input_event = unsafe { mem::transmute(buf) };
+                Ok(Some(Event::new(event.type_, event.code, event.value)))
+            }
+            Ok(_) => Err(io::ErrorKind::UnexpectedEof.into()),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn upload_effect(&mut self, request_id: u32) -> io::Result<Effect> {
+        // SAFETY: `uinput_ff_upload` is plain old data.
+        let mut upload: libc::uinput_ff_upload = unsafe { mem::zeroed() };
+        upload.request_id = request_id;
+        self.ioctl(UI_BEGIN_FF_UPLOAD, &mut upload as *mut _ as libc::c_ulong)?;
+
+        let effect = upload.effect;
+        let (strong_magnitude, weak_magnitude) = if effect.type_ == FF_RUMBLE {
+            // `ff_rumble_effect` sits at the start of the effect union.
+            let bytes = effect.u[0].to_ne_bytes();
+            (
+                u16::from_ne_bytes([bytes[0], bytes[1]]),
+                u16::from_ne_bytes([bytes[2], bytes[3]]),
+            )
+        } else {
+            (0, 0)
+        };
+
+        upload.retval = 0;
+        self.ioctl(UI_END_FF_UPLOAD, &upload as *const _ as libc::c_ulong)?;
+
+        Ok(Effect {
+            id: effect.id,
+            strong_magnitude,
+            weak_magnitude,
+            length: (effect.replay.length != 0)
+                .then(|| Duration::from_millis(effect.replay.length.into())),
+        })
+    }
+
+    fn erase_effect(&mut self, request_id: u32) -> io::Result<i16> {
+        // SAFETY: `uinput_ff_erase` is plain old data.
+        let mut erase: libc::uinput_ff_erase = unsafe { mem::zeroed() };
+        erase.request_id = request_id;
+        self.ioctl(UI_BEGIN_FF_ERASE, &mut erase as *mut _ as libc::c_ulong)?;
+
+        erase.retval = 0;
+        self.ioctl(UI_END_FF_ERASE, &erase as *const _ as libc::c_ulong)?;
+
+        i16::try_from(erase.effect_id).map_err(|_| io::ErrorKind::InvalidData.into())
+    }
+}
+
+impl Drop for UinputDevice {
+    fn drop(&mut self) {
+        if self.created {
+            let _ = self.ioctl(UI_DEV_DESTROY, 0);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    sync::{Arc, Mutex},
+};
+
+use super::{
+    codes::{EV_FF, EV_UINPUT, UI_FF_ERASE, UI_FF_UPLOAD},
+    Capabilities, Device, Effect, Event, Uinput,
+};
+
+/// A `Uinput` that keeps its single virtual device in memory, letting tests
+/// inspect what was written and act as a program uploading and playing effects.
+#[derive(Debug, Clone, Default)]
+pub struct MockUinput {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    capabilities: Option<Capabilities>,
+    alive: bool,
+    written: Vec<Event>,
+    incoming: VecDeque<Event>,
+    uploads: HashMap<u32, Effect>,
+    erases: HashMap<u32, i16>,
+    next_request_id: u32,
+}
+
+impl State {
+    fn request(&mut self, code: u16) -> u32 {
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        self.incoming
+            .push_back(Event::new(EV_UINPUT, code, request_id as i32));
+        request_id
+    }
+}
+
+impl MockUinput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the capabilities the device was last created with.
+    #[must_use]
+    pub fn capabilities(&self) -> Option<Capabilities> {
+        self.state.lock().unwrap().capabilities.clone()
+    }
+
+    #[must_use]
+    pub fn is_alive(&self) -> bool {
+        self.state.lock().unwrap().alive
+    }
+
+    /// Returns and forgets every event written to the device so far.
+    pub fn take_written(&self) -> Vec<Event> {
+        std::mem::take(&mut self.state.lock().unwrap().written)
+    }
+
+    /// Uploads `effect` as a program would with `EVIOCSFF`.
+    pub fn upload(&self, effect: Effect) {
+        let mut state = self.state.lock().unwrap();
+        let request_id = state.request(UI_FF_UPLOAD);
+        state.uploads.insert(request_id, effect);
+    }
+
+    /// Erases effect `id` as a program would with `EVIOCRMFF`.
+    pub fn erase(&self, id: i16) {
+        let mut state = self.state.lock().unwrap();
+        let request_id = state.request(UI_FF_ERASE);
+        state.erases.insert(request_id, id);
+    }
+
+    /// Plays effect `id` `count` times.
+    pub fn play(&self, id: i16, count: i32) {
+        self.send(Event::new(EV_FF, id as u16, count));
+    }
+
+    pub fn stop(&self, id: i16) {
+        self.send(Event::new(EV_FF, id as u16, 0));
+    }
+
+    fn send(&self, event: Event) {
+        self.state.lock().unwrap().incoming.push_back(event);
+    }
+}
+
+impl Uinput for MockUinput {
+    type Device = MockDevice;
+
+    fn create(&self, capabilities: &Capabilities) -> io::Result<MockDevice> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.alive {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "mock device already created",
+            ));
+        }
+
+        state.capabilities = Some(capabilities.clone());
+        state.alive = true;
+
+        Ok(MockDevice {
+            state: self.state.clone(),
+        })
+    }
+}
+
+pub struct MockDevice {
+    state: Arc<Mutex<State>>,
+}
+
+impl Device for MockDevice {
+    fn write(&mut self, events: &[Event]) -> io::Result<()> {
+        self.state.lock().unwrap().written.extend_from_slice(events);
+        Ok(())
+    }
+
+    fn read(&mut self) -> io::Result<Option<Event>> {
+        Ok(self.state.lock().unwrap().incoming.pop_front())
+    }
+
+    fn upload_effect(&mut self, request_id: u32) -> io::Result<Effect> {
+        self.state
+            .lock()
+            .unwrap()
+            .uploads
+            .remove(&request_id)
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn erase_effect(&mut self, request_id: u32) -> io::Result<i16> {
+        self.state
+            .lock()
+            .unwrap()
+            .erases
+            .remove(&request_id)
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
+
+impl Drop for MockDevice {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.alive = false;
+        state.incoming.clear();
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crossbeam::atomic::AtomicCell;
+use gcinput::{Input, Rumble, STICK_RANGE, TRIGGER_RANGE};
+use serde::{Deserialize, Serialize};
+
+pub use super::trigger::TriggerMode;
+
+use super::{rumble::PatternRumbler, Bridge};
+use crate::feeder;
+use codes::{
+    ABS_RX, ABS_RY, ABS_RZ, ABS_X, ABS_Y, ABS_Z, BTN_A, BTN_B, BTN_DPAD_DOWN, BTN_DPAD_LEFT,
+    BTN_DPAD_RIGHT, BTN_DPAD_UP, BTN_START, BTN_THUMBL, BTN_THUMBR, BTN_TR, BTN_X, BTN_Y, EV_ABS,
+    EV_FF, EV_KEY, EV_SYN, EV_UINPUT, FF_RUMBLE, SYN_REPORT, UI_FF_ERASE, UI_FF_UPLOAD,
+};
+
+pub mod codes;
+#[cfg(target_os = "linux")]
+mod linux;
+pub mod mock;
+
+#[cfg(target_os = "linux")]
+pub use linux::{DevUinput, UinputDevice};
+pub use mock::{MockDevice, MockUinput};
+
+const DEVICE_NAME: &str = "gcfeeder GameCube Controller";
+const FF_EFFECTS_MAX: u32 = 16;
+
+const KEYS: [u16; 12] = [
+    BTN_A,
+    BTN_B,
+    BTN_X,
+    BTN_Y,
+    BTN_TR,
+    BTN_START,
+    BTN_THUMBL,
+    BTN_THUMBR,
+    BTN_DPAD_UP,
+    BTN_DPAD_DOWN,
+    BTN_DPAD_LEFT,
+    BTN_DPAD_RIGHT,
+];
+
+/// A single evdev event written to or read from a virtual device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+impl Event {
+    pub const fn new(kind: u16, code: u16, value: i32) -> Self {
+        Self { kind, code, value }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AbsAxis {
+    pub code: u16,
+    pub min: i32,
+    pub max: i32,
+}
+
+/// Everything a virtual device reports, set up before it is created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub name: &'static str,
+    pub keys: Vec<u16>,
+    pub axes: Vec<AbsAxis>,
+    pub ff_effects: Vec<u16>,
+    pub ff_effects_max: u32,
+}
+
+/// A force-feedback effect uploaded to the virtual device by a program.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Effect {
+    pub id: i16,
+    pub strong_magnitude: u16,
+    pub weak_magnitude: u16,
+    /// How long one playback lasts, or `None` to play until stopped.
+    pub length: Option<Duration>,
+}
+
+impl Effect {
+    fn strength(&self) -> u8 {
+        self.strong_magnitude.max(self.weak_magnitude).to_be_bytes()[0]
+    }
+}
+
+/// Creates virtual devices, either through `/dev/uinput` or a mock for tests.
+pub trait Uinput: Send + Sync {
+    type Device: Device;
+
+    fn create(&self, capabilities: &Capabilities) -> io::Result<Self::Device>;
+}
+
+/// A created virtual device, destroyed when dropped.
+pub trait Device: Send {
+    fn write(&mut self, events: &[Event]) -> io::Result<()>;
+
+    /// Returns the next event sent to the device without blocking.
+    fn read(&mut self) -> io::Result<Option<Event>>;
+
+    /// Accepts the effect upload announced by an `EV_UINPUT` `UI_FF_UPLOAD` event.
+    fn upload_effect(&mut self, request_id: u32) -> io::Result<Effect>;
+
+    /// Accepts the effect removal announced by an `EV_UINPUT` `UI_FF_ERASE` event,
+    /// returning the erased effect's id.
+    fn erase_effect(&mut self, request_id: u32) -> io::Result<i16>;
+}
+
+pub struct UinputBridge<U: Uinput> {
+    config: AtomicCell<Config>,
+    uinput: U,
+    state: Mutex<State<U::Device>>,
+}
+
+impl<U: Uinput> UinputBridge<U> {
+    /// Creates the virtual device right away, so a missing `/dev/uinput` or
+    /// permission fails the connection rather than every feed.
+    pub fn new(config: Config, uinput: U) -> io::Result<Self> {
+        let device = uinput.create(&Self::capabilities())?;
+
+        Ok(Self {
+            config: AtomicCell::new(config),
+            uinput,
+            state: Mutex::new(State {
+                device: Some(device),
+                effects: HashMap::new(),
+                playing: HashMap::new(),
+                strength: 0,
+                rumbler: PatternRumbler::new(),
+            }),
+        })
+    }
+
+    pub fn capabilities() -> Capabilities {
+        let stick = |code| AbsAxis {
+            code,
+            min: 0,
+            max: 2 * i32::from(STICK_RANGE.center),
+        };
+        let trigger = |code| AbsAxis {
+            code,
+            min: i32::from(TRIGGER_RANGE.min),
+            max: i32::from(TRIGGER_RANGE.max),
+        };
+
+        Capabilities {
+            name: DEVICE_NAME,
+            keys: KEYS.to_vec(),
+            axes: vec![
+                stick(ABS_X),
+                stick(ABS_Y),
+                stick(ABS_RX),
+                stick(ABS_RY),
+                trigger(ABS_Z),
+                trigger(ABS_RZ),
+            ],
+            ff_effects: vec![FF_RUMBLE],
+            ff_effects_max: FF_EFFECTS_MAX,
+        }
+    }
+
+    /// Converts `input` to the events for one report, ending with `SYN_REPORT`.
+    pub fn input_events(&self, input: &Input) -> Vec<Event> {
+        let triggers = self.config.load().trigger_mode.apply(input);
+        let key = |code, pressed| Event::new(EV_KEY, code, i32::from(pressed));
+        let abs = |code, value| Event::new(EV_ABS, code, i32::from(value));
+        // evdev Y axes point down, so flip them around the stick's center.
+        let abs_flipped = |code, value: u8| {
+            Event::new(
+                EV_ABS,
+                code,
+                2 * i32::from(STICK_RANGE.center) - i32::from(value),
+            )
+        };
+
+        vec![
+            key(BTN_A, input.button_a),
+            key(BTN_B, input.button_b),
+            key(BTN_X, input.button_x),
+            key(BTN_Y, input.button_y),
+            key(BTN_TR, input.button_z),
+            key(BTN_START, input.button_start),
+            key(BTN_THUMBL, triggers.ls),
+            key(BTN_THUMBR, triggers.rs),
+            key(BTN_DPAD_UP, input.button_up),
+            key(BTN_DPAD_DOWN, input.button_down),
+            key(BTN_DPAD_LEFT, input.button_left),
+            key(BTN_DPAD_RIGHT, input.button_right),
+            abs(ABS_X, input.main_stick.x),
+            abs_flipped(ABS_Y, input.main_stick.y),
+            abs(ABS_RX, input.c_stick.x),
+            abs_flipped(ABS_RY, input.c_stick.y),
+            abs(ABS_Z, triggers.l),
+            abs(ABS_RZ, triggers.r),
+            Event::new(EV_SYN, SYN_REPORT, 0),
+        ]
+    }
+}
+
+impl<U: Uinput> Bridge for UinputBridge<U> {
+    fn driver_name(&self) -> &'static str {
+        "uinput"
+    }
+
+    fn feed(&self, input: &Option<Input>) -> super::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(input) = input {
+            let device = match state.device.as_mut() {
+                Some(device) => device,
+                None => state
+                    .device
+                    .insert(self.uinput.create(&Self::capabilities())?),
+            };
+
+            device.write(&self.input_events(input))?;
+            state.process_events()?;
+        } else {
+            state.disconnect();
+        }
+
+        Ok(())
+    }
+
+    fn rumble_state(&self) -> Rumble {
+        let mut state = self.state.lock().unwrap();
+        // Device errors are reported by the next feed instead.
+        let _ = state.process_events();
+        state.rumbler.peek_rumble().into()
+    }
+
+    fn notify_rumble_consumed(&self) {
+        let _ = self.state.lock().unwrap().rumbler.poll_rumble();
+    }
+
+    fn reconfigure(&self, config: &feeder::Config) -> bool {
+        self.config.store(config.uinput_config);
+        true
+    }
+}
+
+struct State<D: Device> {
+    device: Option<D>,
+    effects: HashMap<i16, Effect>,
+    /// Effects currently playing and when they stop, if ever.
+    playing: HashMap<i16, Option<Instant>>,
+    strength: u8,
+    rumbler: PatternRumbler,
+}
+
+impl<D: Device> State<D> {
+    fn process_events(&mut self) -> io::Result<()> {
+        let device = match self.device.as_mut() {
+            Some(device) => device,
+            None => return Ok(()),
+        };
+
+        while let Some(event) = device.read()? {
+            match (event.kind, event.code) {
+                (EV_UINPUT, UI_FF_UPLOAD) => {
+                    let effect = device.upload_effect(event.value as u32)?;
+                    self.effects.insert(effect.id, effect);
+                }
+                (EV_UINPUT, UI_FF_ERASE) => {
+                    let id = device.erase_effect(event.value as u32)?;
+                    self.effects.remove(&id);
+                    self.playing.remove(&id);
+                }
+                (EV_FF, code) => {
+                    let effect = i16::try_from(code)
+                        .ok()
+                        .and_then(|id| self.effects.get(&id));
+
+                    if let Some(effect) = effect {
+                        if event.value > 0 {
+                            let count = u32::try_from(event.value).unwrap_or(1);
+                            let end = effect.length.map(|length| Instant::now() + length * count);
+                            self.playing.insert(effect.id, end);
+                        } else {
+                            self.playing.remove(&effect.id);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        self.playing
+            .retain(|_, end| !matches!(end, Some(end) if *end <= now));
+
+        let strength = self
+            .playing
+            .keys()
+            .filter_map(|id| self.effects.get(id))
+            .map(Effect::strength)
+            .max()
+            .unwrap_or(0);
+        self.set_strength(strength);
+
+        Ok(())
+    }
+
+    fn set_strength(&mut self, strength: u8) {
+        if strength != self.strength {
+            self.strength = strength;
+            self.rumbler.update_strength(strength);
+        }
+    }
+
+    /// Destroys the device along with the effects uploaded to it.
+    fn disconnect(&mut self) {
+        self.device = None;
+        self.effects.clear();
+        self.playing.clear();
+        self.set_strength(0);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    pub trigger_mode: TriggerMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            trigger_mode: TriggerMode::StickClick,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use gcinput::{Input, Rumble, Stick};
+
+    use super::{
+        codes::{ABS_Y, BTN_A, BTN_THUMBL, EV_ABS, EV_KEY},
+        Config, Effect, Event, MockUinput, TriggerMode, UinputBridge,
+    };
+    use crate::bridge::Bridge;
+
+    #[test]
+    fn uinput_bridge_maps_inputs() {
+        let uinput = MockUinput::new();
+        let bridge = UinputBridge::new(Config::default(), uinput.clone()).unwrap();
+        assert!(uinput.is_alive());
+        assert!(UinputBridge::new(Config::default(), uinput.clone()).is_err());
+
+        let input = Input {
+            button_a: true,
+            button_l: true,
+            main_stick: Stick::new(0x80, 0xFF),
+            ..Default::default()
+        };
+
+        bridge.feed(&Some(input)).unwrap();
+        assert_eq!(
+            uinput.capabilities(),
+            Some(UinputBridge::<MockUinput>::capabilities())
+        );
+
+        let written = uinput.take_written();
+        assert!(written.contains(&Event::new(EV_KEY, BTN_A, 1)));
+        assert!(written.contains(&Event::new(EV_KEY, BTN_THUMBL, 1)));
+        assert!(written.contains(&Event::new(EV_ABS, ABS_Y, 1)));
+
+        let digital = UinputBridge::new(
+            Config {
+                trigger_mode: TriggerMode::Digital,
+            },
+            MockUinput::new(),
+        )
+        .unwrap();
+        assert!(digital
+            .input_events(&input)
+            .contains(&Event::new(EV_KEY, BTN_THUMBL, 0)));
+
+        bridge.feed(&None).unwrap();
+        assert!(!uinput.is_alive());
+    }
+
+    #[test]
+    fn uinput_bridge_plays_rumble_effects() {
+        let uinput = MockUinput::new();
+        let bridge = UinputBridge::new(Config::default(), uinput.clone()).unwrap();
+        bridge.feed(&Some(Input::default())).unwrap();
+
+        uinput.upload(Effect {
+            id: 0,
+            strong_magnitude: u16::MAX,
+            weak_magnitude: 0,
+            length: None,
+        });
+        uinput.upload(Effect {
+            id: 1,
+            strong_magnitude: u16::MAX,
+            weak_magnitude: u16::MAX,
+            length: Some(Duration::from_millis(1)),
+        });
+        assert!(matches!(bridge.rumble_state(), Rumble::Off));
+
+        uinput.play(0, 1);
+        assert!(matches!(bridge.rumble_state(), Rumble::On));
+
+        uinput.stop(0);
+        assert!(matches!(bridge.rumble_state(), Rumble::Off));
+
+        uinput.play(1, 1);
+        assert!(matches!(bridge.rumble_state(), Rumble::On));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(matches!(bridge.rumble_state(), Rumble::Off));
+
+        uinput.play(0, 1);
+        uinput.erase(0);
+        assert!(matches!(bridge.rumble_state(), Rumble::Off));
+    }
+}
@@ -4,16 +4,19 @@ use std::{
     thread,
 };
 
-use enum_iterator::Sequence;
+use crossbeam::atomic::AtomicCell;
 use gcinput::{Input, Rumble, STICK_RANGE};
-use serde::{Deserialize, Serialize};
 use vigem_client as client;
 
-use super::{rumble::PatternRumbler, Bridge};
-use crate::util::packed_bools;
+use super::{Config, Pad};
+use crate::{
+    bridge::{rumble::PatternRumbler, Bridge},
+    feeder,
+    util::packed_bools,
+};
 
 pub struct ViGEmBridge {
-    config: Config,
+    config: AtomicCell<Config>,
     device: Device,
 }
 
@@ -22,7 +25,10 @@ impl ViGEmBridge {
         match config.pad {
             Pad::Xbox360 => {
                 let device = Device::new(client)?;
-                Ok(Self { config, device })
+                Ok(Self {
+                    config: AtomicCell::new(config),
+                    device,
+                })
             }
         }
     }
@@ -34,46 +40,8 @@ impl ViGEmBridge {
         scaled.ceil() as i16
     }
 
-    const fn apply_trigger_mode(&self, input: &Input) -> TriggerResult {
-        let l: u8;
-        let r: u8;
-        let mut ls: bool = false;
-        let mut rs: bool = false;
-
-        let Input {
-            left_trigger,
-            right_trigger,
-            button_l,
-            button_r,
-            ..
-        } = *input;
-
-        match self.config.trigger_mode {
-            TriggerMode::Analog => {
-                l = left_trigger;
-                r = right_trigger;
-            }
-            TriggerMode::Digital => {
-                l = if button_l { u8::MAX } else { u8::MIN };
-                r = if button_r { u8::MAX } else { u8::MIN };
-            }
-            TriggerMode::Combination => {
-                l = if button_l { u8::MAX } else { left_trigger };
-                r = if button_r { u8::MAX } else { right_trigger };
-            }
-            TriggerMode::StickClick => {
-                l = left_trigger;
-                r = right_trigger;
-                ls = button_l;
-                rs = button_r;
-            }
-        }
-
-        TriggerResult { l, r, ls, rs }
-    }
-
     fn input_to_xinput(&self, input: &Input) -> client::XGamepad {
-        let result = self.apply_trigger_mode(input);
+        let result = self.config.load().trigger_mode.apply(input);
 
         let buttons = packed_bools!((u16)
             input.button_up,
@@ -111,7 +79,7 @@ impl Bridge for ViGEmBridge {
         "ViGEm"
     }
 
-    fn feed(&self, input: &Option<Input>) -> super::Result<()> {
+    fn feed(&self, input: &Option<Input>) -> crate::bridge::Result<()> {
         let mut target = self.device.target.lock().unwrap();
         let mut thread = self.device.notification_thread.lock().unwrap();
 
@@ -137,6 +105,16 @@ impl Bridge for ViGEmBridge {
     fn notify_rumble_consumed(&self) {
         let _ = self.device.poll_rumble();
     }
+
+    fn reconfigure(&self, config: &feeder::Config) -> bool {
+        // A different pad needs a new target.
+        if config.vigem_config.pad != self.config.load().pad {
+            return false;
+        }
+
+        self.config.store(config.vigem_config);
+        true
+    }
 }
 
 #[derive(Debug)]
@@ -171,7 +149,7 @@ impl Device {
         target: &mut client::XTarget,
         notification_thread: &mut Option<thread::JoinHandle<()>>,
         rumbler: Arc<Mutex<PatternRumbler>>,
-    ) -> super::Result<()> {
+    ) -> crate::bridge::Result<()> {
         let thread = target
             .plugin()
             .and_then(|()| target.wait_ready())
@@ -210,42 +188,3 @@ impl Drop for Device {
         }
     }
 }
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Config {
-    pub pad: Pad,
-    pub trigger_mode: TriggerMode,
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            pad: Pad::Xbox360,
-            trigger_mode: TriggerMode::StickClick,
-        }
-    }
-}
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Sequence)]
-#[serde(rename_all = "lowercase")]
-pub enum Pad {
-    Xbox360,
-    // TODO: DualShock4 support in the ViGEm client library is not complete.
-    // DualShock4,
-}
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Sequence)]
-#[serde(rename_all = "snake_case")]
-pub enum TriggerMode {
-    Analog,
-    Digital,
-    Combination,
-    StickClick,
-}
-
-struct TriggerResult {
-    l: u8,
-    r: u8,
-    ls: bool,
-    rs: bool,
-}
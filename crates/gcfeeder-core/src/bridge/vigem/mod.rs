@@ -0,0 +1,33 @@
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+
+pub use super::trigger::TriggerMode;
+
+#[cfg(windows)]
+mod bridge;
+
+#[cfg(windows)]
+pub use bridge::ViGEmBridge;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    pub pad: Pad,
+    pub trigger_mode: TriggerMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            pad: Pad::Xbox360,
+            trigger_mode: TriggerMode::StickClick,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Sequence)]
+#[serde(rename_all = "lowercase")]
+pub enum Pad {
+    Xbox360,
+    // TODO: DualShock4 support in the ViGEm client library is not complete.
+    // DualShock4,
+}
@@ -7,6 +7,10 @@ use thiserror::Error;
 
 use gcinput::{Input, Stick, STICK_RANGE, TRIGGER_RANGE};
 
+pub use session::Session;
+
+pub mod session;
+
 pub const NOTCHES: usize = 8;
 
 pub static NOTCH_POINTS: Lazy<[[u8; 2]; NOTCHES]> = Lazy::new(|| {
@@ -26,10 +30,14 @@ pub static NOTCH_POINTS: Lazy<[[u8; 2]; NOTCHES]> = Lazy::new(|| {
         .unwrap()
 });
 
-#[derive(Debug, Error)]
+#[derive(Debug, PartialEq, Eq, Error)]
 pub enum Error {
     #[error("bad calibration")]
     BadCalibration,
+    #[error("notch is too close to the center")]
+    NotchTooClose,
+    #[error("trigger was not pressed in further than it was released")]
+    TriggerNotPressed,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,448 @@
+//! The calibration workflow, which prompts for each point in turn and records
+//! the controller's input when A is pressed.
+
+use std::fmt;
+
+use gcinput::Input;
+
+use super::{
+    Error, Result, StickCalibration, SticksCalibration, TriggerCalibration, TriggersCalibration,
+    NOTCHES,
+};
+use crate::{
+    adapter::Transport,
+    feeder::{CalibrationReceiver, Feeder},
+    util::recent_channel::{self as recent, TryRecvError},
+};
+
+pub const NOTCH_NAMES: [&str; NOTCHES] = [
+    "top",
+    "top-right",
+    "right",
+    "bottom-right",
+    "bottom",
+    "bottom-left",
+    "left",
+    "top-left",
+];
+
+/// Notches closer than this to the recorded center are rejected.
+const MIN_NOTCH_DISTANCE: f64 = 16.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Sticks,
+    Triggers,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StickId {
+    Main,
+    C,
+}
+
+impl StickId {
+    const fn of(self, input: &Input) -> [u8; 2] {
+        let stick = match self {
+            Self::Main => input.main_stick,
+            Self::C => input.c_stick,
+        };
+
+        [stick.x, stick.y]
+    }
+}
+
+impl fmt::Display for StickId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Main => write!(f, "main stick"),
+            Self::C => write!(f, "C-stick"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TriggerId {
+    Left,
+    Right,
+}
+
+impl TriggerId {
+    const fn of(self, input: &Input) -> u8 {
+        match self {
+            Self::Left => input.left_trigger,
+            Self::Right => input.right_trigger,
+        }
+    }
+}
+
+impl fmt::Display for TriggerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Left => write!(f, "left trigger"),
+            Self::Right => write!(f, "right trigger"),
+        }
+    }
+}
+
+/// A point to be recorded, which displays as the prompt for it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Step {
+    Center(StickId),
+    /// One of the notches, indexed clockwise from the top.
+    Notch(StickId, usize),
+    Release(TriggerId),
+    Press(TriggerId),
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Center(stick) => write!(f, "Center {} then press A", stick),
+            Self::Notch(stick, i) => write!(
+                f,
+                "Move {} to center then to {} then press A",
+                stick, NOTCH_NAMES[i]
+            ),
+            Self::Release(trigger) => write!(f, "Completely release {} then press A", trigger),
+            Self::Press(trigger) => write!(f, "Press {} all the way in then press A", trigger),
+        }
+    }
+}
+
+/// What a finished session calibrated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Sticks(SticksCalibration),
+    Triggers(TriggersCalibration),
+}
+
+/// Calibrates the sticks or triggers of the controller a feeder is reading.
+pub struct Session {
+    receiver: CalibrationReceiver,
+    kind: Kind,
+    steps: Vec<Step>,
+    /// The input recorded for each step so far.
+    recorded: Vec<Input>,
+    input: Option<Input>,
+    was_a_pressed: bool,
+    rejection: Option<Error>,
+}
+
+impl Session {
+    /// Takes over `feeder`'s inputs, which it outputs as neutral until the
+    /// session is dropped.
+    pub fn start<T: Transport>(feeder: &Feeder<T>, kind: Kind) -> Self {
+        let (sender, receiver) = recent::channel();
+        feeder.start_calibration(sender);
+        Self::new(kind, receiver)
+    }
+
+    pub fn new(kind: Kind, receiver: CalibrationReceiver) -> Self {
+        let steps = match kind {
+            Kind::Sticks => [StickId::Main, StickId::C]
+                .into_iter()
+                .flat_map(|stick| {
+                    std::iter::once(Step::Center(stick))
+                        .chain((0..NOTCHES).map(move |i| Step::Notch(stick, i)))
+                })
+                .collect(),
+            Kind::Triggers => [TriggerId::Left, TriggerId::Right]
+                .into_iter()
+                .flat_map(|trigger| [Step::Release(trigger), Step::Press(trigger)])
+                .collect(),
+        };
+
+        Self {
+            receiver,
+            kind,
+            steps,
+            recorded: Vec::new(),
+            input: None,
+            was_a_pressed: false,
+            rejection: None,
+        }
+    }
+
+    /// Takes the newest input, recording it for the current step if A was just pressed.
+    pub fn update(&mut self) {
+        match self.receiver.try_recv() {
+            Ok(input) => self.input = input,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => self.input = None,
+        }
+
+        let Some(input) = self.input else {
+            self.was_a_pressed = false;
+            return;
+        };
+
+        let confirm = input.button_a && !self.was_a_pressed;
+        self.was_a_pressed = input.button_a;
+
+        if let (true, Some(step)) = (confirm, self.step()) {
+            match self.check(step, &input) {
+                Ok(()) => {
+                    self.recorded.push(input);
+                    self.rejection = None;
+                }
+                Err(e) => self.rejection = Some(e),
+            }
+        }
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// The newest input, or `None` if the controller is disconnected.
+    #[must_use]
+    pub const fn input(&self) -> Option<Input> {
+        self.input
+    }
+
+    /// The step to record next, or `None` once finished.
+    #[must_use]
+    pub fn step(&self) -> Option<Step> {
+        self.steps.get(self.recorded.len()).copied()
+    }
+
+    /// How many steps are recorded, out of how many.
+    #[must_use]
+    pub fn progress(&self) -> (usize, usize) {
+        (self.recorded.len(), self.steps.len())
+    }
+
+    /// Why the last press of A was not recorded, if it was not.
+    #[must_use]
+    pub const fn rejection(&self) -> Option<&Error> {
+        self.rejection.as_ref()
+    }
+
+    /// The center and notches recorded so far for `stick`.
+    #[must_use]
+    pub fn stick_points(&self, stick: StickId) -> Vec<[u8; 2]> {
+        self.recorded_steps()
+            .filter_map(|(step, input)| match step {
+                Step::Center(s) | Step::Notch(s, _) if s == stick => Some(stick.of(input)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The released and pressed values recorded so far for `trigger`.
+    #[must_use]
+    pub fn trigger_markers(&self, trigger: TriggerId) -> Vec<u8> {
+        self.recorded_steps()
+            .filter_map(|(step, input)| match step {
+                Step::Release(t) | Step::Press(t) if t == trigger => Some(trigger.of(input)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The calibration, once every step is recorded.
+    #[must_use]
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.step().is_some() {
+            return None;
+        }
+
+        Some(match self.kind {
+            Kind::Sticks => Outcome::Sticks(SticksCalibration {
+                main_stick: self.stick_calibration(StickId::Main),
+                c_stick: self.stick_calibration(StickId::C),
+            }),
+            Kind::Triggers => Outcome::Triggers(TriggersCalibration {
+                left_trigger: self.trigger_calibration(TriggerId::Left),
+                right_trigger: self.trigger_calibration(TriggerId::Right),
+            }),
+        })
+    }
+
+    fn recorded_steps(&self) -> impl Iterator<Item = (Step, &Input)> {
+        self.steps.iter().copied().zip(self.recorded.iter())
+    }
+
+    fn recorded(&self, step: Step) -> Option<&Input> {
+        self.recorded_steps()
+            .find_map(|(s, input)| (s == step).then_some(input))
+    }
+
+    fn check(&self, step: Step, input: &Input) -> Result<()> {
+        match step {
+            Step::Notch(stick, _) => {
+                let center = self.recorded(Step::Center(stick)).map(|c| stick.of(c));
+                let [x, y] = stick.of(input);
+
+                if let Some([cx, cy]) = center {
+                    let distance =
+                        (f64::from(x) - f64::from(cx)).hypot(f64::from(y) - f64::from(cy));
+
+                    if distance < MIN_NOTCH_DISTANCE {
+                        return Err(Error::NotchTooClose);
+                    }
+                }
+            }
+            Step::Press(trigger) => {
+                let released = self.recorded(Step::Release(trigger)).map(|r| trigger.of(r));
+
+                if released.is_some_and(|released| trigger.of(input) <= released) {
+                    return Err(Error::TriggerNotPressed);
+                }
+            }
+            Step::Center(_) | Step::Release(_) => {}
+        }
+
+        Ok(())
+    }
+
+    fn stick_calibration(&self, stick: StickId) -> StickCalibration {
+        let point = |step| self.recorded(step).map_or([0; 2], |input| stick.of(input));
+
+        StickCalibration {
+            notch_points: std::array::from_fn(|i| point(Step::Notch(stick, i))),
+            center: point(Step::Center(stick)),
+        }
+    }
+
+    fn trigger_calibration(&self, trigger: TriggerId) -> TriggerCalibration {
+        let value = |step| self.recorded(step).map_or(0, |input| trigger.of(input));
+
+        TriggerCalibration {
+            min: value(Step::Release(trigger)),
+            max: value(Step::Press(trigger)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gcinput::{Input, Stick};
+
+    use super::{Kind, Outcome, Session, Step, StickId, TriggerId, MIN_NOTCH_DISTANCE};
+    use crate::{
+        calibration::{
+            Error, StickCalibration, SticksCalibration, TriggerCalibration, TriggersCalibration,
+            NOTCHES,
+        },
+        feeder::CalibrationSender,
+        util::recent_channel as recent,
+    };
+
+    fn send(session: &mut Session, sender: &CalibrationSender, value: u8, button_a: bool) {
+        sender
+            .send(Some(Input {
+                left_trigger: value,
+                right_trigger: value,
+                button_a,
+                ..Default::default()
+            }))
+            .unwrap();
+        session.update();
+    }
+
+    #[test]
+    fn trigger_session_validates_presses() {
+        let (sender, receiver) = recent::channel();
+        let mut session = Session::new(Kind::Triggers, receiver);
+
+        send(&mut session, &sender, 0x20, true);
+        send(&mut session, &sender, 0x20, false);
+        send(&mut session, &sender, 0x10, true);
+        assert_eq!(session.rejection(), Some(&Error::TriggerNotPressed));
+
+        send(&mut session, &sender, 0xE0, false);
+        send(&mut session, &sender, 0xE0, true);
+        assert_eq!(session.rejection(), None);
+        assert_eq!(session.trigger_markers(TriggerId::Left), [0x20, 0xE0]);
+        assert_eq!(session.step(), Some(Step::Release(TriggerId::Right)));
+        assert_eq!(session.progress(), (2, 4));
+        assert_eq!(session.outcome(), None);
+
+        send(&mut session, &sender, 0x20, false);
+        send(&mut session, &sender, 0x20, true);
+        send(&mut session, &sender, 0xE0, false);
+        send(&mut session, &sender, 0xE0, true);
+
+        let trigger = TriggerCalibration {
+            min: 0x20,
+            max: 0xE0,
+        };
+        assert_eq!(
+            session.outcome(),
+            Some(Outcome::Triggers(TriggersCalibration {
+                left_trigger: trigger,
+                right_trigger: trigger,
+            }))
+        );
+    }
+
+    #[test]
+    fn stick_session_records_every_notch() {
+        let (sender, receiver) = recent::channel();
+        let mut session = Session::new(Kind::Sticks, receiver);
+        // Records `stick` at `point`, releasing A first so the press registers.
+        let record = |session: &mut Session, stick: StickId, [x, y]: [u8; 2]| {
+            for button_a in [false, true] {
+                let mut input = Input {
+                    button_a,
+                    ..Default::default()
+                };
+                match stick {
+                    StickId::Main => input.main_stick = Stick::new(x, y),
+                    StickId::C => input.c_stick = Stick::new(x, y),
+                }
+                sender.send(Some(input)).unwrap();
+                session.update();
+            }
+        };
+
+        let calibration = |center: [u8; 2]| StickCalibration {
+            notch_points: std::array::from_fn(|i| {
+                let angle = std::f64::consts::TAU * i as f64 / NOTCHES as f64;
+                let offset = |v: f64| (80.0 * v).round() as i16;
+                [
+                    (i16::from(center[0]) + offset(angle.sin())) as u8,
+                    (i16::from(center[1]) + offset(angle.cos())) as u8,
+                ]
+            }),
+            center,
+        };
+        let main = calibration([0x80, 0x7E]);
+        let c = calibration([0x82, 0x80]);
+
+        for (stick, calibration) in [(StickId::Main, main), (StickId::C, c)] {
+            assert_eq!(session.step(), Some(Step::Center(stick)));
+            record(&mut session, stick, calibration.center);
+
+            for (i, &notch) in calibration.notch_points.iter().enumerate() {
+                if stick == StickId::Main && i == 3 {
+                    let [x, y] = calibration.center;
+                    let near = (MIN_NOTCH_DISTANCE / 2.0) as u8;
+                    record(&mut session, stick, [x + near, y - near]);
+                    assert_eq!(session.rejection(), Some(&Error::NotchTooClose));
+                    assert_eq!(session.step(), Some(Step::Notch(stick, 3)));
+                }
+
+                assert_eq!(session.step(), Some(Step::Notch(stick, i)));
+                record(&mut session, stick, notch);
+                assert_eq!(session.rejection(), None);
+            }
+
+            let mut points = vec![calibration.center];
+            points.extend(calibration.notch_points);
+            assert_eq!(session.stick_points(stick), points);
+        }
+
+        assert_eq!(session.progress(), (18, 18));
+        assert_eq!(
+            session.outcome(),
+            Some(Outcome::Sticks(SticksCalibration {
+                main_stick: main,
+                c_stick: c,
+            }))
+        );
+    }
+}
@@ -1,34 +1,40 @@
 use std::{
+    collections::BTreeSet,
     mem,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, Mutex,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use crossbeam::channel;
 use enclose::enclose;
 use enum_iterator::Sequence;
-use gcinput::Input;
-use log::warn;
+use gcinput::{Input, Rumble};
+use log::{debug, info, warn};
 use rayon::prelude::{IntoParallelRefMutIterator, ParallelIterator};
-use rusb::UsbContext;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::{
-    adapter::poller::{self, ERROR_TIMEOUT},
+    adapter::{
+        poller::{self, ERROR_TIMEOUT, OPEN_RETRY_TIMEOUT},
+        ControllerStatus, Port, Transport,
+    },
     bridge::{
-        self,
-        vigem::{Config as ViGEmConfig, ViGEmBridge},
-        Error as BridgeError,
+        self, dsu::Config as DsuConfig, uinput::Config as UinputConfig,
+        vigem::Config as ViGEmConfig, Error as BridgeError,
     },
     calibration::{SticksCalibration, TriggersCalibration},
+    hotkey::{self, Chords, Hotkey, Profiles},
     mapping::{
         self,
-        layers::{self, AnalogScaling, CenterCalibration, EssInversion},
+        layers::EssInversion,
+        pipeline::{self, CenterCalibrationConfig, LayerConfig, LayerSpec},
     },
     util::{
+        metrics::{Histogram, Summary},
         recent_channel::{self as recent, RecvTimeoutError, TrySendError},
         AverageTimer,
     },
@@ -44,47 +50,63 @@ pub type CalibrationSender = recent::Sender<Option<Input>>;
 pub type CalibrationReceiver = recent::Receiver<Option<Input>>;
 pub type Layer = dyn mapping::Layer + Send;
 
-// TODO: Make this come from the poll rate on the adapter.
+/// How long to wait for input until the adapter's report interval has been measured.
 pub const INPUT_TIMEOUT: Duration = Duration::from_millis(8);
 
-pub struct Feeder<T: UsbContext + 'static> {
+/// The longest wait between attempts to connect a bridge that keeps failing.
+const CONNECT_RETRY_MAX: Duration = Duration::from_secs(8);
+
+pub struct Feeder<T: Transport + 'static> {
     context: Arc<Context<T>>,
     thread: Option<thread::JoinHandle<()>>,
 }
 
-impl<T: UsbContext + 'static> Feeder<T> {
+impl<T: Transport + 'static> Feeder<T> {
     pub fn new(config: Config, listener: poller::Listener<T>) -> Self {
-        let internal_layers: Vec<Box<Layer>> = vec![Box::<CenterCalibration>::default()];
-        let mut layers: Vec<Box<Layer>> = Vec::new();
-
-        if (config.analog_scale.abs() - 1.0).abs() >= 1e-10 {
-            layers.push(Box::new(AnalogScaling::new(config.analog_scale)));
-        }
-
-        if let Some(map) = config.ess.inversion_mapping {
-            layers.push(Box::new(map));
-        }
+        Self::start(config, listener, None)
+    }
 
-        if config.calibration.enabled {
-            layers.push(Box::new(layers::Calibration::new(
-                config.calibration.stick_data,
-                config.calibration.trigger_data,
-            )));
-        }
+    /// Feeds `bridge` along with the configured drivers. Unlike theirs, `bridge`
+    /// is not reconnected if it fails.
+    pub fn with_bridge(config: Config, listener: poller::Listener<T>, bridge: Box<Bridge>) -> Self {
+        Self::start(config, listener, Some(bridge))
+    }
 
-        let context = Arc::new(Context::new(config, listener));
+    fn start(config: Config, listener: poller::Listener<T>, bridge: Option<Box<Bridge>>) -> Self {
+        let context = Arc::new(Context::new(listener));
         let thread = Some(thread::spawn(
-            enclose!((context) move || context.feed_loop(config.rumble, internal_layers, layers)),
+            enclose!((context) move || context.feed_loop(config, bridge)),
         ));
 
         Self { context, thread }
     }
 
+    /// Applies `config` before the next feed without recreating the feeder.
+    ///
+    /// The layers and rumble settings are replaced, and bridges whose driver is
+    /// still listed keep their connection unless their new options cannot be
+    /// applied in place.
+    pub fn update_config(&self, config: Config) {
+        *self.context.config_update.lock().unwrap() = Some(config);
+    }
+
     #[must_use]
     pub fn average_feed_time(&self) -> Option<Duration> {
         *self.context.average_feed_time.lock().unwrap()
     }
 
+    /// Returns the distribution of feed times since the feeder started. Feeds
+    /// that took longer than two of the adapter's report intervals, and so let
+    /// a report go by, are counted as overruns.
+    #[must_use]
+    pub fn feed_times(&self) -> Option<Summary> {
+        self.context.feed_times.lock().unwrap().summary()
+    }
+
+    pub fn clear_feed_times(&self) {
+        self.context.feed_times.lock().unwrap().clear();
+    }
+
     pub fn on_feed(&self, callback: impl FnMut(&Record) + Send + 'static) {
         let mut callbacks = self.context.callbacks.lock().unwrap();
         callbacks.push(Box::new(callback));
@@ -100,12 +122,35 @@ impl<T: UsbContext + 'static> Feeder<T> {
         self.context.connected.load(Ordering::Acquire)
     }
 
+    /// Replaces the hotkeys checked before each feed.
+    pub fn set_hotkeys(&self, hotkeys: Vec<Hotkey>) {
+        *self.context.hotkeys_update.lock().unwrap() = Some(hotkeys);
+    }
+
+    /// Replaces the profiles that hotkeys switch between. The profile in use is
+    /// left as is until a hotkey or [`Feeder::update_config`] replaces it.
+    pub fn set_profiles(&self, profiles: Profiles) {
+        *self.context.profiles_update.lock().unwrap() = Some(profiles);
+    }
+
+    /// Sends every hotkey triggered from now on to `sender`, after the feeder
+    /// has applied it.
+    pub fn send_hotkeys(&self, sender: channel::Sender<hotkey::Event>) {
+        self.context.hotkey_senders.lock().unwrap().push(sender);
+    }
+
+    /// Whether records should include each layer's output, which costs an
+    /// allocation per feed.
+    pub fn set_tracing(&self, tracing: bool) {
+        self.context.tracing.store(tracing, Ordering::Release);
+    }
+
     pub fn start_calibration(&self, sender: CalibrationSender) {
         *self.context.calibration_sender.lock().unwrap() = Some(sender);
     }
 }
 
-impl<T: UsbContext> Drop for Feeder<T> {
+impl<T: Transport> Drop for Feeder<T> {
     fn drop(&mut self) {
         self.context.stop_flag.store(true, Ordering::Release);
 
@@ -115,36 +160,55 @@ impl<T: UsbContext> Drop for Feeder<T> {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct Record {
     pub raw_input: Option<Input>,
     pub layered_input: Option<Input>,
+    /// The output of every layer in the order they were applied, if tracing.
+    pub trace: Option<Vec<LayerStep>>,
+    pub status: ControllerStatus,
     pub feed_time: Duration,
 }
 
-struct Context<T: UsbContext> {
-    pub config: Config,
+#[derive(Debug, Copy, Clone)]
+pub struct LayerStep {
+    pub layer: &'static str,
+    pub output: Option<Input>,
+}
+
+struct Context<T: Transport> {
     pub listener: poller::Listener<T>,
     pub stop_flag: AtomicBool,
     pub connected: AtomicBool,
+    pub tracing: AtomicBool,
+    pub config_update: Mutex<Option<Config>>,
+    pub hotkeys_update: Mutex<Option<Vec<Hotkey>>>,
+    pub profiles_update: Mutex<Option<Profiles>>,
+    pub hotkey_senders: Mutex<Vec<channel::Sender<hotkey::Event>>>,
     pub calibration_sender: Mutex<Option<CalibrationSender>>,
     pub callbacks: Mutex<Vec<Box<Callback>>>,
     pub senders: Mutex<Vec<Sender>>,
     pub average_feed_time: Mutex<Option<Duration>>,
+    pub feed_times: Mutex<Histogram>,
     pub thread_pool: rayon::ThreadPool,
 }
 
-impl<T: UsbContext> Context<T> {
-    pub fn new(config: Config, listener: poller::Listener<T>) -> Self {
+impl<T: Transport> Context<T> {
+    pub fn new(listener: poller::Listener<T>) -> Self {
         Self {
-            config,
             listener,
             stop_flag: Default::default(),
             connected: Default::default(),
+            tracing: Default::default(),
+            config_update: Default::default(),
+            hotkeys_update: Default::default(),
+            profiles_update: Default::default(),
+            hotkey_senders: Default::default(),
             callbacks: Default::default(),
             calibration_sender: Default::default(),
             senders: Default::default(),
             average_feed_time: Default::default(),
+            feed_times: Mutex::new(Histogram::new(2 * INPUT_TIMEOUT)),
             thread_pool: rayon::ThreadPoolBuilder::new()
                 .num_threads(2)
                 .build()
@@ -152,144 +216,426 @@ impl<T: UsbContext> Context<T> {
         }
     }
 
-    pub fn feed_loop(
-        &self,
-        rumble: RumbleSetting,
-        mut internal_layers: Vec<Box<Layer>>,
-        mut layers: Vec<Box<Layer>>,
-    ) {
-        let mut bridge: Option<Box<Bridge>> = None;
+    pub fn feed_loop(&self, mut config: Config, bridge: Option<Box<Bridge>>) {
+        let mut disabled_layers = BTreeSet::new();
+        let mut layers = config.build_layers();
+        let mut chords = Chords::default();
+        let mut profiles = Profiles::default();
+        let mut pulse_until = None;
+        let mut outputs = bridge
+            .map(Output::given)
+            .into_iter()
+            .chain(config.drivers.iter().copied().map(Output::new))
+            .collect::<Vec<_>>();
         let mut timer = AverageTimer::start(0.9).unwrap();
 
         while !self.stop_flag.load(Ordering::Acquire) {
-            let record = {
-                let bridge = match self.bridge_or_reload(&mut bridge) {
-                    Ok(b) => b,
-                    Err(e) => {
-                        warn!("Failed to connect to bridge: {}", e);
-                        thread::sleep(ERROR_TIMEOUT);
-                        continue;
-                    }
-                };
+            if let Some(update) = self.config_update.lock().unwrap().take() {
+                apply_config(update, &mut config, &mut layers, &mut outputs);
+            }
+
+            if let Some(hotkeys) = self.hotkeys_update.lock().unwrap().take() {
+                chords = Chords::new(hotkeys);
+            }
+
+            if let Some(update) = self.profiles_update.lock().unwrap().take() {
+                profiles = update;
+            }
 
-                timer.reset();
+            let primary = self.listener.on_primary();
+            for output in outputs.iter_mut() {
+                output.connect_if_due(&config, self.listener.port(), primary);
+            }
+
+            let connected = outputs.iter().any(Output::is_connected);
+            self.connected.store(connected, Ordering::Release);
+
+            if !connected {
+                thread::sleep(ERROR_TIMEOUT);
+                continue;
+            }
+
+            timer.reset();
+
+            match pulse_until {
+                Some(until) if Instant::now() < until => self.listener.set_rumble(Rumble::On),
+                _ => {
+                    if pulse_until.take().is_some() {
+                        self.listener.reset_rumble();
+                    }
 
-                match rumble {
-                    RumbleSetting::On => {
-                        self.listener.set_rumble(bridge.rumble_state());
+                    match config.rumble {
+                        RumbleSetting::On => {
+                            self.listener
+                                .set_rumble(config.rumble_source.rumble_state(&outputs));
+                        }
+                        RumbleSetting::Off => {}
                     }
-                    RumbleSetting::Off => {}
                 }
+            }
 
+            for bridge in outputs.iter().filter_map(|o| o.bridge.as_ref()) {
                 bridge.notify_rumble_consumed();
+            }
 
-                match self.listener.recv_timeout(INPUT_TIMEOUT) {
-                    Ok(input) => {
-                        let apply_layers = |input: Option<Input>, layers: &mut [Box<Layer>]| {
-                            layers
-                                .iter_mut()
-                                .fold(input, |input, layer| layer.apply(input))
-                        };
-
-                        let input = apply_layers(input, &mut internal_layers);
-                        let layered = apply_layers(input, &mut layers);
-
-                        let (input, layered) = {
-                            let mut calibration_sender = self.calibration_sender.lock().unwrap();
+            let input_timeout = self.listener.report_interval().unwrap_or(INPUT_TIMEOUT);
 
-                            if let Some(sender) = calibration_sender.as_ref() {
-                                if let Err(TrySendError::Disconnected(_)) = sender.try_send(input) {
-                                    *calibration_sender = None;
-                                }
+            let record = match self.listener.recv_timeout(input_timeout) {
+                Ok(poller::InputMessage { mut input, status }) => {
+                    for action in chords.process(&mut input, Instant::now()) {
+                        let port = self.listener.port();
 
-                                (Some(Input::default()), Some(Input::default()))
+                        if let hotkey::Action::ToggleLayer { layer } = &action {
+                            if disabled_layers.remove(layer) {
+                                info!("Turned {} layers on", layer);
                             } else {
-                                (input, layered)
+                                info!("Turned {} layers off", layer);
+                                disabled_layers.insert(layer.clone());
                             }
-                        };
+                        }
 
-                        bridge.feed(&layered).map(|()| Record {
-                            raw_input: input,
-                            layered_input: layered,
-                            feed_time: timer.read(),
-                        })
+                        let profile = profiles.switched_by(&action).map(str::to_owned);
+                        if let Some(name) = profile.as_ref() {
+                            info!("Switched port {:?} to profile '{}'", port, name);
+                            let update = profiles.list[name].clone();
+                            apply_config(update, &mut config, &mut layers, &mut outputs);
+                            profiles.selected = name.clone();
+                        }
+
+                        pulse_until = Some(Instant::now() + hotkey::PULSE);
+
+                        if let Some(controller) = self.listener.controller() {
+                            let event = hotkey::Event {
+                                controller,
+                                action,
+                                profile,
+                            };
+                            self.hotkey_senders
+                                .lock()
+                                .unwrap()
+                                .retain(|sender| sender.send(event.clone()).is_ok());
+                        }
                     }
-                    Err(RecvTimeoutError::Disconnected) => break,
-                    _ => continue,
-                }
-            };
 
-            match record {
-                Ok(record) => {
+                    let mut trace = self.tracing.load(Ordering::Acquire).then(Vec::new);
+                    // Calibration is measured on centered input, as the layers see it.
+                    let mut centered = input;
+                    let mut layered = input;
+
+                    // Layers that are toggled off are skipped rather than dropped, so
+                    // they pick up where they left off once toggled back on.
+                    for (kind, layer) in layers
+                        .iter_mut()
+                        .filter(|(kind, _)| !disabled_layers.contains(kind))
                     {
-                        self.thread_pool.join(
-                            || {
-                                let mut callbacks = self.callbacks.lock().unwrap();
-                                callbacks
-                                    .par_iter_mut()
-                                    .for_each(|callback| callback(&record));
-                            },
-                            || {
-                                let mut senders = self.senders.lock().unwrap();
-
-                                senders.retain(|sender| {
-                                    !matches!(
-                                        sender.try_send(record),
-                                        Err(TrySendError::Disconnected(_))
-                                    )
-                                });
-                            },
-                        );
+                        layered = layer.apply(layered);
+
+                        if kind == CenterCalibrationConfig::KIND {
+                            centered = layered;
+                        }
+
+                        if let Some(trace) = trace.as_mut() {
+                            trace.push(LayerStep {
+                                layer: layer.name(),
+                                output: layered,
+                            });
+                        }
                     }
 
-                    *self.average_feed_time.lock().unwrap() = Some(timer.lap());
-                }
-                Err(e) => {
-                    bridge = None;
-                    warn!("Bridge error: {}", e);
-                    continue;
+                    let (input, layered, trace) = {
+                        let mut calibration_sender = self.calibration_sender.lock().unwrap();
+
+                        if let Some(sender) = calibration_sender.as_ref() {
+                            if let Err(TrySendError::Disconnected(_)) = sender.try_send(centered) {
+                                *calibration_sender = None;
+                            }
+
+                            (Some(Input::default()), Some(Input::default()), None)
+                        } else {
+                            (input, layered, trace)
+                        }
+                    };
+
+                    for output in outputs.iter_mut() {
+                        output.feed(&layered);
+                    }
+
+                    Record {
+                        raw_input: input,
+                        layered_input: layered,
+                        trace,
+                        status,
+                        feed_time: timer.read(),
+                    }
                 }
+                Err(RecvTimeoutError::Disconnected) => break,
+                _ => continue,
+            };
+
+            self.thread_pool.join(
+                || {
+                    let mut callbacks = self.callbacks.lock().unwrap();
+                    callbacks
+                        .par_iter_mut()
+                        .for_each(|callback| callback(&record));
+                },
+                || {
+                    let mut senders = self.senders.lock().unwrap();
+
+                    senders.retain(|sender| {
+                        !matches!(
+                            sender.try_send(record.clone()),
+                            Err(TrySendError::Disconnected(_))
+                        )
+                    });
+                },
+            );
+
+            {
+                let mut feed_times = self.feed_times.lock().unwrap();
+                feed_times.set_budget(2 * input_timeout);
+                feed_times.record(timer.read());
             }
+            *self.average_feed_time.lock().unwrap() = Some(timer.lap());
         }
 
         self.connected.store(false, Ordering::Release);
     }
+}
 
-    fn bridge_or_reload<'a>(&self, bridge: &'a mut Option<Box<Bridge>>) -> Result<&'a mut Bridge> {
-        if let Some(bridge) = bridge {
-            Ok(bridge.as_mut())
-        } else {
-            self.connected.store(false, Ordering::Release);
-            let b = match self.config.driver {
-                Driver::ViGEm => Box::new(ViGEmBridge::new(
-                    self.config.vigem_config,
-                    vigem_client::Client::connect()?,
-                )?),
-            };
-            self.connected.store(true, Ordering::Release);
+/// One of the bridges a feeder outputs to, which connects and reconnects
+/// independently of the others.
+struct Output {
+    /// The driver to reconnect with, if the bridge was not given to the feeder.
+    driver: Option<Driver>,
+    bridge: Option<Box<Bridge>>,
+    retry_at: Option<Instant>,
+    /// How long the last failed connect waited, doubling with each failure in a row.
+    retry_delay: Option<Duration>,
+}
+
+impl Output {
+    fn new(driver: Driver) -> Self {
+        Self {
+            driver: Some(driver),
+            bridge: None,
+            retry_at: None,
+            retry_delay: None,
+        }
+    }
+
+    fn given(bridge: Box<Bridge>) -> Self {
+        Self {
+            driver: None,
+            bridge: Some(bridge),
+            retry_at: None,
+            retry_delay: None,
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.bridge.is_some()
+    }
+
+    fn connect_if_due(&mut self, config: &Config, port: Port, primary: bool) {
+        let Some(driver) = self.driver else {
+            return;
+        };
+
+        if self.bridge.is_some() || self.retry_at.is_some_and(|t| Instant::now() < t) {
+            return;
+        }
+
+        match connect(driver, config, port, primary) {
+            Ok(bridge) => {
+                if self.retry_delay.is_some() {
+                    info!("Connected to {} bridge", driver.name());
+                }
+
+                self.bridge = Some(bridge);
+                self.retry_at = None;
+                self.retry_delay = None;
+            }
+            Err(e) => {
+                let delay = match self.retry_delay {
+                    Some(delay) => {
+                        debug!("Failed to connect to {} bridge: {}", driver.name(), e);
+                        (delay * 2).min(CONNECT_RETRY_MAX)
+                    }
+                    None => {
+                        warn!("Failed to connect to {} bridge: {}", driver.name(), e);
+                        OPEN_RETRY_TIMEOUT
+                    }
+                };
 
-            Ok(bridge.insert(b).as_mut())
+                self.retry_at = Some(Instant::now() + delay);
+                self.retry_delay = Some(delay);
+            }
         }
     }
+
+    fn reconfigure(&mut self, config: &Config) {
+        let Some(bridge) = self.bridge.as_ref() else {
+            return;
+        };
+
+        // Given bridges cannot be reconnected, so they keep what they can apply.
+        if !bridge.reconfigure(config) && self.driver.is_some() {
+            self.bridge = None;
+            self.retry_at = None;
+        }
+    }
+
+    fn feed(&mut self, input: &Option<Input>) {
+        if let Some(bridge) = self.bridge.as_ref() {
+            if let Err(e) = bridge.feed(input) {
+                warn!("{} bridge error: {}", bridge.driver_name(), e);
+                self.bridge = None;
+            }
+        }
+    }
+}
+
+/// Swaps `config` for `update`, keeping the connections and centering that
+/// carry over.
+fn apply_config(
+    update: Config,
+    config: &mut Config,
+    layers: &mut Vec<(String, Box<Layer>)>,
+    outputs: &mut Vec<Output>,
+) {
+    let previous = mem::replace(layers, update.build_layers());
+    keep_centering(layers, previous);
+    *outputs = reconcile_outputs(mem::take(outputs), &update);
+    *config = update;
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+/// Hands the center calibration layers of `previous` on to those of `layers`,
+/// in order, since their centering is measured when the controller connects
+/// rather than configured.
+fn keep_centering(layers: &mut [(String, Box<Layer>)], previous: Vec<(String, Box<Layer>)>) {
+    let mut centering = previous
+        .into_iter()
+        .filter(|(kind, _)| kind == CenterCalibrationConfig::KIND);
+
+    for (_, layer) in layers
+        .iter_mut()
+        .filter(|(kind, _)| kind == CenterCalibrationConfig::KIND)
+    {
+        if let Some((_, previous)) = centering.next() {
+            *layer = previous;
+        }
+    }
+}
+
+/// Orders outputs after the drivers in `config`, keeping the connections of
+/// drivers that are still listed and dropping the rest.
+fn reconcile_outputs(outputs: Vec<Output>, config: &Config) -> Vec<Output> {
+    let (given, mut previous): (Vec<_>, Vec<_>) =
+        outputs.into_iter().partition(|o| o.driver.is_none());
+
+    let configured = config
+        .drivers
+        .iter()
+        .map(|&driver| {
+            previous
+                .iter()
+                .position(|o| o.driver == Some(driver))
+                .map_or_else(|| Output::new(driver), |i| previous.remove(i))
+        })
+        .collect::<Vec<_>>();
+
+    given
+        .into_iter()
+        .chain(configured)
+        .map(|mut output| {
+            output.reconfigure(config);
+            output
+        })
+        .collect()
+}
+
+/// Connects a bridge for `port`. Dolphin's pipes are named after the port alone,
+/// so only controllers on the `primary` adapter feed them.
+fn connect(driver: Driver, config: &Config, port: Port, primary: bool) -> Result<Box<Bridge>> {
+    if driver == Driver::Pipe && !primary {
+        return Err(BridgeError::Pipe(std::io::Error::new(
+            std::io::ErrorKind::AddrInUse,
+            "only the primary adapter feeds Dolphin's pipes",
+        )));
+    }
+
+    Ok(match driver {
+        #[cfg(windows)]
+        Driver::ViGEm => Box::new(bridge::vigem::ViGEmBridge::new(
+            config.vigem_config,
+            vigem_client::Client::connect()?,
+        )?),
+        #[cfg(target_os = "linux")]
+        Driver::Uinput => Box::new(bridge::uinput::UinputBridge::new(
+            config.uinput_config,
+            bridge::uinput::DevUinput::default(),
+        )?),
+        Driver::Dsu => Box::new(
+            bridge::dsu::DsuBridge::new(config.dsu_config, port).map_err(BridgeError::Dsu)?,
+        ),
+        #[cfg(unix)]
+        Driver::Pipe => Box::new(bridge::pipe::PipeBridge::new(port).map_err(BridgeError::Pipe)?),
+        Driver::None => Box::new(bridge::null::NullBridge::new()),
+        driver => return Err(BridgeError::Unsupported(driver.name())),
+    })
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub driver: Driver,
+    /// Every driver is fed, with the first listed one driving rumble by default.
+    #[serde(alias = "driver", deserialize_with = "one_or_many")]
+    pub drivers: Vec<Driver>,
     pub rumble: RumbleSetting,
+    #[serde(default)]
+    pub rumble_source: RumbleSource,
     pub analog_scale: f64,
+    /// Applied in order after the adapter's inputs are centered.
+    #[serde(default = "pipeline::default_layers")]
+    pub layers: Vec<LayerSpec>,
     pub vigem_config: ViGEmConfig,
+    #[serde(default)]
+    pub uinput_config: UinputConfig,
+    #[serde(default)]
+    pub dsu_config: DsuConfig,
     pub calibration: CalibrationConfig,
     pub ess: EssConfig,
 }
 
+impl Config {
+    /// Builds the profile's layers along with their types, skipping any that
+    /// fail to build.
+    fn build_layers(&self) -> Vec<(String, Box<Layer>)> {
+        self.layers
+            .iter()
+            .filter_map(|spec| {
+                let layer = spec.build(self).unwrap_or_else(|e| {
+                    warn!("Skipping layer: {}", e);
+                    None
+                });
+
+                layer.map(|layer| (spec.kind.clone(), layer))
+            })
+            .collect()
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
-            driver: Default::default(),
+            drivers: vec![Driver::default()],
             rumble: Default::default(),
+            rumble_source: Default::default(),
             analog_scale: 1.0,
+            layers: pipeline::default_layers(),
             vigem_config: Default::default(),
+            uinput_config: Default::default(),
+            dsu_config: Default::default(),
             calibration: Default::default(),
             ess: Default::default(),
         }
@@ -300,11 +646,121 @@ impl Default for Config {
 #[serde(rename_all = "lowercase")]
 pub enum Driver {
     ViGEm,
+    Uinput,
+    Dsu,
+    Pipe,
+    None,
+}
+
+impl Driver {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::ViGEm => "ViGEm",
+            Self::Uinput => "uinput",
+            Self::Dsu => "DSU",
+            Self::Pipe => "Dolphin pipe",
+            Self::None => "None",
+        }
+    }
 }
 
 impl Default for Driver {
     fn default() -> Self {
-        Self::ViGEm
+        if cfg!(target_os = "linux") {
+            Self::Uinput
+        } else {
+            Self::ViGEm
+        }
+    }
+}
+
+/// Accepts the single driver profiles used to have as well as a list.
+fn one_or_many<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<Driver>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(Driver),
+        Many(Vec<Driver>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(driver) => vec![driver],
+        OneOrMany::Many(drivers) => drivers,
+    })
+}
+
+/// Which bridge's rumble state drives the controller's motor.
+///
+/// Written as `first`, `any` or the name of a driver, since TOML has no way
+/// to write an enum variant holding a value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize, Sequence)]
+#[serde(from = "RumbleSourceRepr", into = "RumbleSourceRepr")]
+pub enum RumbleSource {
+    /// The first connected bridge.
+    #[default]
+    First,
+    /// Any bridge that wants to rumble.
+    Any,
+    /// Only the bridge of the given driver.
+    Driver(Driver),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RumbleSourceRepr {
+    Policy(RumblePolicy),
+    Driver(Driver),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RumblePolicy {
+    First,
+    Any,
+}
+
+impl From<RumbleSourceRepr> for RumbleSource {
+    fn from(repr: RumbleSourceRepr) -> Self {
+        match repr {
+            RumbleSourceRepr::Policy(RumblePolicy::First) => Self::First,
+            RumbleSourceRepr::Policy(RumblePolicy::Any) => Self::Any,
+            RumbleSourceRepr::Driver(driver) => Self::Driver(driver),
+        }
+    }
+}
+
+impl From<RumbleSource> for RumbleSourceRepr {
+    fn from(source: RumbleSource) -> Self {
+        match source {
+            RumbleSource::First => Self::Policy(RumblePolicy::First),
+            RumbleSource::Any => Self::Policy(RumblePolicy::Any),
+            RumbleSource::Driver(driver) => Self::Driver(driver),
+        }
+    }
+}
+
+impl RumbleSource {
+    fn rumble_state(self, outputs: &[Output]) -> Rumble {
+        let mut connected = outputs
+            .iter()
+            .filter_map(|o| Some((o.driver, o.bridge.as_ref()?)));
+
+        match self {
+            Self::First => connected
+                .next()
+                .map_or(Rumble::Off, |(_, bridge)| bridge.rumble_state()),
+            // Ask every bridge so each of them keeps up with its rumble events.
+            Self::Any => connected
+                .fold(false, |on, (_, bridge)| {
+                    bridge.rumble_state() == Rumble::On || on
+                })
+                .into(),
+            Self::Driver(driver) => connected
+                .find(|&(d, _)| d == Some(driver))
+                .map_or(Rumble::Off, |(_, bridge)| bridge.rumble_state()),
+        }
     }
 }
 
@@ -332,3 +788,354 @@ impl Default for RumbleSetting {
         Self::On
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use gcinput::{Input, Rumble};
+    use serde::{
+        de::{value, IntoDeserializer},
+        Deserialize,
+    };
+
+    use super::{
+        connect, one_or_many, reconcile_outputs, Config, Driver, Feeder, Output, Profiles,
+        RumbleSource,
+    };
+    use crate::{
+        adapter::{
+            poller::Poller,
+            transport::{Script, ScriptedBus, ScriptedTransport},
+            AdapterId, ControllerId, Port, PAYLOAD_LEN,
+        },
+        bridge::{self, null::NullBridge, Bridge},
+        feeder,
+        util::recent_channel as recent,
+    };
+
+    struct Rumbling;
+
+    impl Bridge for Rumbling {
+        fn driver_name(&self) -> &'static str {
+            "Rumbling"
+        }
+
+        fn feed(&self, _input: &Option<Input>) -> bridge::Result<()> {
+            Ok(())
+        }
+
+        fn rumble_state(&self) -> Rumble {
+            Rumble::On
+        }
+
+        fn notify_rumble_consumed(&self) {}
+
+        fn reconfigure(&self, _config: &feeder::Config) -> bool {
+            false
+        }
+    }
+
+    /// Scripts a controller holding A, with its main stick centered in the first
+    /// report and pushed right in the rest.
+    fn poller() -> Poller<ScriptedTransport> {
+        let payload = |main_x: u8| {
+            let mut payload = [0_u8; PAYLOAD_LEN];
+            payload[0] = rusb::constants::LIBUSB_DT_HID;
+            payload[1..10]
+                .copy_from_slice(&[0x10, 0x01, 0x00, main_x, 0x80, 0x80, 0x80, 0x00, 0x00]);
+            payload
+        };
+
+        let script =
+            Script::new(AdapterId::new(1, vec![1])).with_interval(Duration::from_millis(1));
+        script.push_payload(payload(0x80));
+        for _ in 0..1024 {
+            script.push_payload(payload(0xC0));
+        }
+
+        Poller::new(ScriptedBus::from(script))
+    }
+
+    fn config() -> Config {
+        Config {
+            drivers: vec![Driver::None],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn null_driver_runs_callbacks() {
+        let poller = poller();
+        let feeder = Feeder::new(config(), poller.add_listener(Port::One));
+        let (sender, receiver) = recent::channel();
+        feeder.send_on_feed(sender);
+
+        let record = receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(feeder.connected());
+        assert!(record.layered_input.unwrap().button_a);
+    }
+
+    #[test]
+    fn tracing_records_each_layer() {
+        let poller = poller();
+        let feeder = Feeder::new(
+            Config {
+                analog_scale: 0.5,
+                ..config()
+            },
+            poller.add_listener(Port::One),
+        );
+        let (sender, receiver) = recent::channel();
+        feeder.send_on_feed(sender);
+
+        let record = receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(record.trace.is_none());
+
+        feeder.set_tracing(true);
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let trace = loop {
+            let record = receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+            if let Some(trace) = record.trace {
+                assert_eq!(trace.last().unwrap().output, record.layered_input);
+                break trace;
+            }
+            assert!(Instant::now() < deadline, "no trace was recorded");
+        };
+
+        let layers = trace.iter().map(|step| step.layer).collect::<Vec<_>>();
+        assert_eq!(layers, ["Centered", "Scaled"]);
+    }
+
+    #[test]
+    fn recording_bridge_stores_fed_inputs() {
+        let poller = poller();
+        let bridge = NullBridge::recording();
+        let _feeder = Feeder::with_bridge(
+            config(),
+            poller.add_listener(Port::One),
+            Box::new(bridge.clone()),
+        );
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while bridge.recorded().is_empty() {
+            assert!(Instant::now() < deadline, "nothing was fed to the bridge");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(bridge.recorded()[0].unwrap().button_a);
+    }
+
+    #[test]
+    fn rumble_source_picks_bridges() {
+        let outputs = [
+            Output::new(Driver::Dsu),
+            Output {
+                driver: Some(Driver::None),
+                ..Output::given(Box::new(NullBridge::new()))
+            },
+            Output::given(Box::new(Rumbling)),
+        ];
+
+        assert_eq!(RumbleSource::First.rumble_state(&outputs), Rumble::Off);
+        assert_eq!(RumbleSource::Any.rumble_state(&outputs), Rumble::On);
+        assert_eq!(
+            RumbleSource::Driver(Driver::None).rumble_state(&outputs),
+            Rumble::Off
+        );
+        assert_eq!(
+            RumbleSource::Driver(Driver::Dsu).rumble_state(&outputs),
+            Rumble::Off
+        );
+        assert_eq!(RumbleSource::First.rumble_state(&outputs[2..]), Rumble::On);
+    }
+
+    #[test]
+    fn drivers_accept_one_or_many() {
+        let one: Result<_, value::Error> = one_or_many("dsu".into_deserializer());
+        let many: Result<_, value::Error> = one_or_many(vec!["none", "pipe"].into_deserializer());
+
+        assert_eq!(one, Ok(vec![Driver::Dsu]));
+        assert_eq!(many, Ok(vec![Driver::None, Driver::Pipe]));
+
+        let any: Result<_, value::Error> = RumbleSource::deserialize("any".into_deserializer());
+        let dsu: Result<_, value::Error> = RumbleSource::deserialize("dsu".into_deserializer());
+        assert_eq!(any, Ok(RumbleSource::Any));
+        assert_eq!(dsu, Ok(RumbleSource::Driver(Driver::Dsu)));
+    }
+
+    #[test]
+    fn update_config_swaps_layers() {
+        let poller = poller();
+        let feeder = Feeder::new(config(), poller.add_listener(Port::One));
+        let (sender, receiver) = recent::channel();
+        feeder.send_on_feed(sender);
+
+        let main_x = || {
+            let record = receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+            record.layered_input.unwrap().main_stick.x
+        };
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while main_x() != 0xC0 {
+            assert!(Instant::now() < deadline, "stick was never pushed");
+        }
+
+        feeder.update_config(Config {
+            analog_scale: 0.5,
+            ..config()
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while main_x() != 0xA0 {
+            assert!(Instant::now() < deadline, "new layers were never applied");
+        }
+        assert!(feeder.connected());
+    }
+
+    #[test]
+    fn hotkeys_switch_profiles_in_the_feeder() {
+        let poller = poller();
+        let feeder = Feeder::new(config(), poller.add_listener(Port::One));
+        let (sender, receiver) = recent::channel();
+        feeder.send_on_feed(sender);
+        let (hotkey_sender, hotkey_events) = crossbeam::channel::unbounded();
+        feeder.send_hotkeys(hotkey_sender);
+
+        let scaled = Config {
+            analog_scale: 0.5,
+            ..config()
+        };
+        feeder.set_profiles(Profiles {
+            list: [("default", config()), ("scaled", scaled)]
+                .into_iter()
+                .map(|(name, profile)| (name.to_owned(), profile))
+                .collect(),
+            selected: "default".to_owned(),
+        });
+        feeder.set_hotkeys(vec![toml::from_str(
+            r#"
+            chord = ["a"]
+            hold_ms = 0
+            action = "next_profile"
+            "#,
+        )
+        .unwrap()]);
+
+        let event = hotkey_events.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.profile.as_deref(), Some("scaled"));
+        // The primary listener reports the adapter that fired the hotkey.
+        assert_eq!(
+            event.controller,
+            ControllerId {
+                adapter: AdapterId::new(1, vec![1]),
+                port: Port::One,
+            }
+        );
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        loop {
+            let record = receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+            if record.layered_input.unwrap().main_stick.x == 0xA0 {
+                break;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "the switched profile was never applied"
+            );
+        }
+    }
+
+    #[test]
+    fn profiles_round_trip_through_toml() {
+        let mut config = Config {
+            rumble_source: RumbleSource::Driver(Driver::Dsu),
+            ..Default::default()
+        };
+        config.layers[0]
+            .params
+            .insert("scale".to_owned(), toml::Value::from(0.5));
+
+        for layers in [config.layers.clone(), Vec::new()] {
+            let config = Config {
+                layers,
+                ..config.clone()
+            };
+            let text = toml::to_string(&config).unwrap();
+            let parsed: Config = toml::from_str(&text).unwrap();
+
+            assert_eq!(parsed.layers, config.layers);
+            assert_eq!(parsed.rumble_source, config.rumble_source);
+        }
+    }
+
+    #[test]
+    fn only_the_primary_adapter_feeds_pipes() {
+        // Pipes are named after the port, so a second adapter would share them.
+        let refused = connect(Driver::Pipe, &Config::default(), Port::One, false);
+        assert!(matches!(
+            refused,
+            Err(bridge::Error::Pipe(e)) if e.kind() == std::io::ErrorKind::AddrInUse
+        ));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn failed_connects_back_off() {
+        use super::CONNECT_RETRY_MAX;
+        use crate::adapter::poller::OPEN_RETRY_TIMEOUT;
+
+        // ViGEm is unsupported here, so connecting always fails.
+        let config = Config::default();
+        let mut output = Output::new(Driver::ViGEm);
+
+        output.connect_if_due(&config, Port::One, true);
+        assert!(!output.is_connected());
+        assert_eq!(output.retry_delay, Some(OPEN_RETRY_TIMEOUT));
+
+        output.connect_if_due(&config, Port::One, true);
+        assert_eq!(output.retry_delay, Some(OPEN_RETRY_TIMEOUT));
+
+        for _ in 0..8 {
+            output.retry_at = None;
+            output.connect_if_due(&config, Port::One, true);
+        }
+        assert_eq!(output.retry_delay, Some(CONNECT_RETRY_MAX));
+    }
+
+    #[test]
+    fn reconcile_keeps_listed_drivers() {
+        let connected = |driver| Output {
+            driver: Some(driver),
+            ..Output::given(Box::new(NullBridge::new()))
+        };
+
+        let outputs = vec![
+            connected(Driver::None),
+            connected(Driver::Pipe),
+            Output::given(Box::new(Rumbling)),
+        ];
+        let config = Config {
+            drivers: vec![Driver::Dsu, Driver::None],
+            ..Default::default()
+        };
+
+        let outputs = reconcile_outputs(outputs, &config);
+        let drivers = outputs.iter().map(|o| o.driver).collect::<Vec<_>>();
+        assert_eq!(drivers, [None, Some(Driver::Dsu), Some(Driver::None)]);
+        assert!(outputs[0].is_connected());
+        assert!(!outputs[1].is_connected());
+        assert!(outputs[2].is_connected());
+
+        let mut output = Output {
+            driver: Some(Driver::Dsu),
+            ..Output::given(Box::new(Rumbling))
+        };
+        output.reconfigure(&config);
+        assert!(!output.is_connected());
+    }
+}
@@ -0,0 +1,327 @@
+//! Button chords that switch profiles or toggle layers from the controller.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use gcinput::{Button, Input, TRIGGER_RANGE};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{adapter::ControllerId, feeder};
+
+/// How long the controller rumbles to confirm a hotkey.
+pub const PULSE: Duration = Duration::from_millis(200);
+
+/// Holding every button of `chord` for `hold_ms` milliseconds triggers `action`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hotkey {
+    pub chord: Vec<Button>,
+    #[serde(default = "default_hold_ms")]
+    pub hold_ms: u64,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+impl Hotkey {
+    #[must_use]
+    pub const fn hold(&self) -> Duration {
+        Duration::from_millis(self.hold_ms)
+    }
+}
+
+const fn default_hold_ms() -> u64 {
+    1000
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    NextProfile,
+    PreviousProfile,
+    SelectProfile {
+        profile: String,
+    },
+    /// Turns the profile's layers of this type off, or back on.
+    ToggleLayer {
+        layer: String,
+    },
+}
+
+/// A hotkey triggered by `controller`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub controller: ControllerId,
+    pub action: Action,
+    /// The profile the feeder switched to, if the action switched profiles.
+    pub profile: Option<String>,
+}
+
+/// The profiles a feeder's hotkeys switch between.
+#[derive(Clone, Default)]
+pub struct Profiles {
+    pub list: BTreeMap<String, feeder::Config>,
+    /// The name of the profile in use.
+    pub selected: String,
+}
+
+impl Profiles {
+    /// The name of the profile `step` places after the selected one, wrapping
+    /// around the alphabetically ordered list.
+    #[must_use]
+    pub fn cycled(&self, step: isize) -> Option<&str> {
+        let names = self.list.keys().map(String::as_str).collect::<Vec<_>>();
+
+        let current = names
+            .iter()
+            .position(|&name| name == self.selected)
+            .unwrap_or_default();
+        let len = isize::try_from(names.len()).ok().filter(|&len| len > 0)?;
+        let index = (isize::try_from(current).ok()? + step).rem_euclid(len);
+
+        names.get(usize::try_from(index).ok()?).copied()
+    }
+
+    /// The name of the profile `action` switches to, if it switches profiles.
+    #[must_use]
+    pub fn switched_by(&self, action: &Action) -> Option<&str> {
+        match action {
+            Action::NextProfile => self.cycled(1),
+            Action::PreviousProfile => self.cycled(-1),
+            Action::SelectProfile { profile } => {
+                let name = self
+                    .list
+                    .get_key_value(profile)
+                    .map(|(name, _)| name.as_str());
+                if name.is_none() {
+                    warn!("Hotkey selects missing profile '{}'", profile);
+                }
+
+                name
+            }
+            Action::ToggleLayer { .. } => None,
+        }
+    }
+}
+
+/// Tracks how long each hotkey's chord has been held.
+///
+/// A chord's buttons are only hidden once all of them are held, since holding
+/// back a partial chord would delay those buttons for every other use. The ones
+/// pressed before the rest of the chord reach the output until then.
+#[derive(Debug, Default)]
+pub struct Chords {
+    chords: Vec<Chord>,
+    /// Buttons of a fully pressed chord, hidden from the output until released.
+    suppressed: Vec<Button>,
+}
+
+#[derive(Debug)]
+struct Chord {
+    hotkey: Hotkey,
+    held_since: Option<Instant>,
+    fired: bool,
+}
+
+impl Chord {
+    fn release(&mut self) {
+        self.held_since = None;
+        self.fired = false;
+    }
+}
+
+impl Chords {
+    pub fn new(hotkeys: Vec<Hotkey>) -> Self {
+        Self {
+            chords: hotkeys
+                .into_iter()
+                .filter(|hotkey| !hotkey.chord.is_empty())
+                .map(|hotkey| Chord {
+                    hotkey,
+                    held_since: None,
+                    fired: false,
+                })
+                .collect(),
+            suppressed: Vec::new(),
+        }
+    }
+
+    /// Returns the actions of chords held long enough as of `now`, once per
+    /// press, and releases the buttons of any fully pressed chord in `input`.
+    pub fn process(&mut self, input: &mut Option<Input>, now: Instant) -> Vec<Action> {
+        let Some(input) = input.as_mut() else {
+            for chord in self.chords.iter_mut() {
+                chord.release();
+            }
+            self.suppressed.clear();
+            return Vec::new();
+        };
+
+        let mut actions = Vec::new();
+
+        for chord in self.chords.iter_mut() {
+            if !chord.hotkey.chord.iter().all(|&b| input.button(b)) {
+                chord.release();
+                continue;
+            }
+
+            let held_since = *chord.held_since.get_or_insert(now);
+
+            for &button in chord.hotkey.chord.iter() {
+                if !self.suppressed.contains(&button) {
+                    self.suppressed.push(button);
+                }
+            }
+
+            if !chord.fired && now.duration_since(held_since) >= chord.hotkey.hold() {
+                chord.fired = true;
+                actions.push(chord.hotkey.action.clone());
+            }
+        }
+
+        self.suppressed.retain(|&button| input.button(button));
+        for &button in self.suppressed.iter() {
+            input.set_button(button, false);
+
+            // The analog triggers would still press L and R in most games.
+            match button {
+                Button::L => input.left_trigger = TRIGGER_RANGE.min,
+                Button::R => input.right_trigger = TRIGGER_RANGE.min,
+                _ => {}
+            }
+        }
+
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use gcinput::{Button, Input, TRIGGER_RANGE};
+
+    use super::{Action, Chords, Hotkey, Profiles};
+    use crate::feeder;
+
+    #[test]
+    fn chords_fire_once_and_are_suppressed() {
+        let hotkey: Hotkey = toml::from_str(
+            r#"
+            chord = ["l", "r", "start", "up"]
+            action = "select_profile"
+            profile = "melee"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(hotkey.hold(), Duration::from_secs(1));
+        assert_eq!(
+            toml::from_str(&toml::to_string(&hotkey).unwrap()),
+            Ok(hotkey.clone())
+        );
+
+        let mut chords = Chords::new(vec![hotkey]);
+        let start = Instant::now();
+        let press = |buttons: &[Button]| {
+            let mut input = Input::default();
+            for &button in buttons {
+                input.set_button(button, true);
+            }
+            // Fully pressed triggers click L and R.
+            if input.button_l {
+                input.left_trigger = u8::MAX;
+            }
+            if input.button_r {
+                input.right_trigger = u8::MAX;
+            }
+            Some(input)
+        };
+
+        let mut input = press(&[Button::L, Button::R, Button::Start]);
+        assert!(chords.process(&mut input, start).is_empty());
+        assert!(input.unwrap().button_start);
+
+        let chord = [Button::L, Button::R, Button::Start, Button::Up, Button::A];
+        let mut input = press(&chord);
+        assert!(chords.process(&mut input, start).is_empty());
+        // Both the trigger buttons and their analog values are released.
+        assert_eq!(input, press(&[Button::A]));
+        assert_eq!(input.unwrap().left_trigger, TRIGGER_RANGE.min);
+        assert_eq!(input.unwrap().right_trigger, TRIGGER_RANGE.min);
+
+        let later = start + Duration::from_secs(1);
+        let mut input = press(&chord);
+        assert_eq!(
+            chords.process(&mut input, later),
+            [Action::SelectProfile {
+                profile: "melee".to_owned()
+            }]
+        );
+        assert!(chords.process(&mut press(&chord), later).is_empty());
+
+        let mut input = press(&[Button::Start, Button::B]);
+        assert!(chords.process(&mut input, later).is_empty());
+        assert_eq!(input, press(&[Button::B]));
+
+        let mut input = press(&[Button::B]);
+        chords.process(&mut input, later);
+        let mut input = press(&[Button::Start]);
+        chords.process(&mut input, later);
+        assert_eq!(input, press(&[Button::Start]));
+    }
+
+    #[test]
+    fn profiles_cycle_in_alphabetical_order() {
+        let mut profiles = Profiles {
+            list: ["melee", "default", "brawl"]
+                .into_iter()
+                .map(|name| (name.to_owned(), feeder::Config::default()))
+                .collect(),
+            selected: "default".to_owned(),
+        };
+
+        assert_eq!(profiles.switched_by(&Action::NextProfile), Some("melee"));
+        assert_eq!(
+            profiles.switched_by(&Action::PreviousProfile),
+            Some("brawl")
+        );
+        profiles.selected = "melee".to_owned();
+        assert_eq!(profiles.cycled(1), Some("brawl"));
+
+        let missing = Action::SelectProfile {
+            profile: "smash".to_owned(),
+        };
+        assert_eq!(profiles.switched_by(&missing), None);
+        assert_eq!(Profiles::default().cycled(1), None);
+    }
+
+    #[test]
+    fn partial_chords_pass_through() {
+        let hotkey: Hotkey = toml::from_str(
+            r#"
+            chord = ["l", "r"]
+            hold_ms = 0
+            action = "next_profile"
+            "#,
+        )
+        .unwrap();
+        let mut chords = Chords::new(vec![hotkey]);
+        let now = Instant::now();
+
+        let l = Input {
+            button_l: true,
+            ..Default::default()
+        };
+        let mut input = Some(l);
+        assert!(chords.process(&mut input, now).is_empty());
+        assert_eq!(input, Some(l));
+
+        let mut input = Some(Input {
+            button_r: true,
+            ..l
+        });
+        assert_eq!(chords.process(&mut input, now), [Action::NextProfile]);
+        assert_eq!(input, Some(Input::default()));
+    }
+}
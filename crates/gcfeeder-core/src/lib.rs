@@ -3,5 +3,6 @@ pub mod adapter;
 pub mod bridge;
 pub mod calibration;
 pub mod feeder;
+pub mod hotkey;
 pub mod mapping;
 pub mod util;
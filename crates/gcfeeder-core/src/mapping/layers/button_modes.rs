@@ -0,0 +1,227 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use gcinput::{Button, Input};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::mapping;
+
+/// How a button is pressed while, or after, the controller holds it.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Mode {
+    /// Repeats the press `rate_hz` times a second while held, staying pressed
+    /// for the `duty` fraction of each repeat.
+    Turbo {
+        #[serde(default = "default_rate_hz", deserialize_with = "rate_hz")]
+        rate_hz: f64,
+        #[serde(default = "default_duty", deserialize_with = "duty")]
+        duty: f64,
+    },
+    /// Each press latches the button, and the next one releases it.
+    Toggle,
+    /// Each press holds the button for at least `hold_ms` milliseconds.
+    Hold {
+        #[serde(default = "default_hold_ms")]
+        hold_ms: u64,
+    },
+}
+
+const fn default_rate_hz() -> f64 {
+    15.0
+}
+
+const fn default_duty() -> f64 {
+    0.5
+}
+
+const fn default_hold_ms() -> u64 {
+    500
+}
+
+/// Rejects rates that would never repeat, including `NaN`.
+fn rate_hz<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let rate_hz = f64::deserialize(deserializer)?;
+
+    if rate_hz.is_finite() && rate_hz > 0.0 {
+        Ok(rate_hz)
+    } else {
+        Err(de::Error::custom(format!(
+            "rate_hz must be above 0, got {}",
+            rate_hz
+        )))
+    }
+}
+
+/// Rejects duties that would leave the button always pressed or always
+/// released, including `NaN`.
+fn duty<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let duty = f64::deserialize(deserializer)?;
+
+    if duty > 0.0 && duty < 1.0 {
+        Ok(duty)
+    } else {
+        Err(de::Error::custom(format!(
+            "duty must be between 0 and 1, got {}",
+            duty
+        )))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct State {
+    was_pressed: bool,
+    /// When the button was last pressed down.
+    pressed_at: Option<Instant>,
+    latched: bool,
+}
+
+impl Mode {
+    fn apply(self, state: &mut State, pressed: bool, now: Instant) -> bool {
+        let just_pressed = pressed && !state.was_pressed;
+        state.was_pressed = pressed;
+
+        if just_pressed {
+            state.pressed_at = Some(now);
+            state.latched = !state.latched;
+        }
+
+        let elapsed = state.pressed_at.map(|at| now.duration_since(at));
+
+        match self {
+            Self::Turbo { rate_hz, duty } => {
+                // Phased from the press, so it always starts pressed.
+                pressed && elapsed.is_some_and(|e| (e.as_secs_f64() * rate_hz).fract() < duty)
+            }
+            Self::Toggle => state.latched,
+            Self::Hold { hold_ms } => {
+                pressed || elapsed.is_some_and(|e| e < Duration::from_millis(hold_ms))
+            }
+        }
+    }
+}
+
+/// Turbo, toggle and hold modes for individual buttons, timed by when each
+/// input is fed. Buttons that are not listed are passed on as they are.
+#[derive(Debug, Clone, Default)]
+pub struct ButtonModes {
+    modes: BTreeMap<Button, Mode>,
+    states: BTreeMap<Button, State>,
+}
+
+impl ButtonModes {
+    pub fn new(modes: BTreeMap<Button, Mode>) -> Self {
+        Self {
+            modes,
+            states: BTreeMap::new(),
+        }
+    }
+
+    pub fn process(&mut self, input: Input, now: Instant) -> Input {
+        let mut processed = input;
+
+        for (&button, &mode) in self.modes.iter() {
+            let state = self.states.entry(button).or_default();
+            processed.set_button(button, mode.apply(state, input.button(button), now));
+        }
+
+        processed
+    }
+
+    /// Releases every latched or held button.
+    pub fn reset(&mut self) {
+        self.states.clear();
+    }
+}
+
+impl mapping::Layer for ButtonModes {
+    fn name(&self) -> &'static str {
+        "Moded"
+    }
+
+    fn apply(&mut self, input: Option<Input>) -> Option<Input> {
+        match input {
+            Some(input) => Some(self.process(input, Instant::now())),
+            None => {
+                self.reset();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use gcinput::Input;
+
+    use super::ButtonModes;
+    use crate::mapping::{pipeline::ButtonModesConfig, Layer};
+
+    #[test]
+    fn modes_follow_the_feed_time() {
+        let config: ButtonModesConfig = toml::from_str(
+            r#"
+            a = { mode = "turbo", rate_hz = 10.0, duty = 0.25 }
+            b = { mode = "toggle" }
+            x = { mode = "hold", hold_ms = 100 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            toml::from_str(&toml::to_string(&config).unwrap()),
+            Ok(config.clone())
+        );
+
+        for mode in [
+            r#"{ mode = "turbo", rate_hz = 0.0 }"#,
+            r#"{ mode = "turbo", rate_hz = nan }"#,
+            r#"{ mode = "turbo", duty = 1.0 }"#,
+            r#"{ mode = "turbo", duty = -nan }"#,
+        ] {
+            let config = format!("a = {}", mode);
+            assert!(toml::from_str::<ButtonModesConfig>(&config).is_err());
+        }
+
+        let mut modes = ButtonModes::new(config.modes);
+
+        let start = Instant::now();
+        let mut at =
+            |ms: u64, input: Input| modes.process(input, start + Duration::from_millis(ms));
+        let held = Input {
+            button_a: true,
+            button_b: true,
+            button_x: true,
+            button_y: true,
+            ..Default::default()
+        };
+
+        let frame = at(0, held);
+        assert!(frame.button_a && frame.button_b && frame.button_x && frame.button_y);
+        let frame = at(30, held);
+        assert!(!frame.button_a && frame.button_b);
+        assert!(at(100, held).button_a);
+
+        let frame = at(150, Input::default());
+        assert!(!frame.button_a && frame.button_b && !frame.button_x);
+
+        let tap = Input {
+            button_x: true,
+            ..Default::default()
+        };
+        assert!(at(160, tap).button_x);
+        assert!(at(170, Input::default()).button_x);
+        let frame = at(270, Input::default());
+        assert!(frame.button_b && !frame.button_x);
+
+        assert!(!at(280, held).button_b);
+        at(290, Input::default());
+        assert!(at(300, held).button_b);
+
+        assert_eq!(modes.apply(None), None);
+        assert!(!modes.process(Input::default(), start).button_b);
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use gcinput::{Button, Input};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::mapping;
+
+/// Presses every target of a held button instead of the button itself.
+/// Buttons that are not listed press themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ButtonRemap {
+    pub map: BTreeMap<Button, Targets>,
+}
+
+/// The buttons one button presses, written as a single button or as a list,
+/// which can be empty.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Targets(pub Vec<Button>);
+
+impl Serialize for Targets {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Targets {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(Button),
+            Many(Vec<Button>),
+        }
+
+        Ok(Self(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(button) => vec![button],
+            OneOrMany::Many(buttons) => buttons,
+        }))
+    }
+}
+
+impl ButtonRemap {
+    pub fn new(map: BTreeMap<Button, Targets>) -> Self {
+        Self { map }
+    }
+
+    /// Whether every button presses only itself.
+    pub fn is_identity(&self) -> bool {
+        self.map
+            .iter()
+            .all(|(&button, targets)| targets.0 == [button])
+    }
+
+    pub fn remap(&self, input: Input) -> Input {
+        let mut remapped = input;
+
+        for button in Button::ALL {
+            remapped.set_button(button, false);
+        }
+
+        for button in Button::ALL.into_iter().filter(|&b| input.button(b)) {
+            match self.map.get(&button) {
+                Some(Targets(targets)) => {
+                    for &target in targets {
+                        remapped.set_button(target, true);
+                    }
+                }
+                None => remapped.set_button(button, true),
+            }
+        }
+
+        remapped
+    }
+}
+
+impl mapping::Layer for ButtonRemap {
+    fn name(&self) -> &'static str {
+        "Remapped"
+    }
+
+    fn apply(&mut self, input: Option<Input>) -> Option<Input> {
+        input.map(|input| self.remap(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gcinput::{Button, Input};
+
+    use super::ButtonRemap;
+    use crate::mapping::pipeline::ButtonRemapConfig;
+
+    #[test]
+    fn remaps_swap_split_and_drop_buttons() {
+        let config: ButtonRemapConfig = toml::from_str(
+            r#"
+            a = "b"
+            b = "a"
+            z = ["z", "r"]
+            start = []
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            toml::from_str(&toml::to_string(&config).unwrap()),
+            Ok(config.clone())
+        );
+
+        let remap = ButtonRemap::new(config.map);
+        assert!(!remap.is_identity());
+
+        let press = |buttons: &[Button]| {
+            let mut input = Input::default();
+            for &button in buttons {
+                input.set_button(button, true);
+            }
+            input
+        };
+
+        assert_eq!(
+            remap.remap(press(&[Button::A, Button::Z, Button::Start, Button::X])),
+            press(&[Button::B, Button::Z, Button::R, Button::X])
+        );
+        assert_eq!(
+            remap.remap(press(&[Button::A, Button::B])),
+            press(&[Button::A, Button::B])
+        );
+
+        let identity: ButtonRemapConfig = toml::from_str(r#"y = "y""#).unwrap();
+        assert!(ButtonRemap::new(identity.map).is_identity());
+    }
+}
@@ -0,0 +1,265 @@
+use enum_iterator::Sequence;
+use gcinput::{Input, Stick, STICK_RANGE, TRIGGER_RANGE};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::mapping;
+
+/// Deadzones for each stick and trigger, which rescale what is left of their
+/// range so the full output range stays reachable.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Deadzone {
+    pub main_stick: StickDeadzone,
+    pub c_stick: StickDeadzone,
+    pub left_trigger: TriggerDeadzone,
+    pub right_trigger: TriggerDeadzone,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize, Sequence)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadzoneShape {
+    /// Each axis is centered on its own, which snaps the stick to the axes.
+    Axial,
+    /// The stick is centered until it leaves the circle, then jumps to its position.
+    Radial,
+    /// The stick is centered until it leaves the circle, then eases out from the center.
+    #[default]
+    ScaledRadial,
+}
+
+/// Fractions of a stick's radius.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct StickDeadzone {
+    pub shape: DeadzoneShape,
+    /// The stick reads as centered inside this.
+    pub inner: f64,
+    /// The stick reads as fully pushed outside this.
+    pub outer: f64,
+}
+
+impl Default for StickDeadzone {
+    fn default() -> Self {
+        Self {
+            shape: Default::default(),
+            inner: 0.0,
+            outer: 1.0,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StickDeadzone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Fields {
+            #[serde(default)]
+            shape: DeadzoneShape,
+            #[serde(default)]
+            inner: f64,
+            #[serde(default = "full")]
+            outer: f64,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        let (inner, outer) = clamp_edges(fields.inner, fields.outer);
+
+        Ok(Self {
+            shape: fields.shape,
+            inner,
+            outer,
+        })
+    }
+}
+
+impl StickDeadzone {
+    pub fn apply(&self, stick: Stick) -> Stick {
+        let center = f64::from(STICK_RANGE.center);
+        let radius = f64::from(STICK_RANGE.radius);
+
+        let x = ((f64::from(stick.x) - center) / radius).clamp(-1.0, 1.0);
+        let y = ((f64::from(stick.y) - center) / radius).clamp(-1.0, 1.0);
+        let magnitude = x.hypot(y);
+
+        let (x, y) = match self.shape {
+            DeadzoneShape::Axial => (
+                x.signum() * rescale(x.abs(), self.inner, self.outer),
+                y.signum() * rescale(y.abs(), self.inner, self.outer),
+            ),
+            _ if magnitude <= self.inner => (0.0, 0.0),
+            // An outer edge of 0 reads every push as a full one.
+            _ if self.outer <= 0.0 => (x / magnitude, y / magnitude),
+            DeadzoneShape::Radial => (x / self.outer, y / self.outer),
+            // Past the outer edge each axis saturates on its own, like the gate's corners.
+            _ if magnitude >= self.outer => (x / self.outer, y / self.outer),
+            DeadzoneShape::ScaledRadial => {
+                let scale = rescale(magnitude, self.inner, self.outer) / magnitude;
+                (x * scale, y * scale)
+            }
+        };
+
+        let denormalize = |n: f64| n.clamp(-1.0, 1.0).mul_add(radius, center).round() as u8;
+        Stick::new(denormalize(x), denormalize(y))
+    }
+}
+
+/// Fractions of a trigger's travel.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct TriggerDeadzone {
+    /// The trigger reads as released below this.
+    pub inner: f64,
+    /// The trigger reads as fully pressed above this.
+    pub outer: f64,
+}
+
+impl Default for TriggerDeadzone {
+    fn default() -> Self {
+        Self {
+            inner: 0.0,
+            outer: 1.0,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TriggerDeadzone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Fields {
+            #[serde(default)]
+            inner: f64,
+            #[serde(default = "full")]
+            outer: f64,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        let (inner, outer) = clamp_edges(fields.inner, fields.outer);
+
+        Ok(Self { inner, outer })
+    }
+}
+
+impl TriggerDeadzone {
+    pub fn apply(&self, value: u8) -> u8 {
+        let min = f64::from(TRIGGER_RANGE.min);
+        let range = f64::from(TRIGGER_RANGE.max) - min;
+
+        let value = ((f64::from(value) - min) / range).clamp(0.0, 1.0);
+        rescale(value, self.inner, self.outer)
+            .mul_add(range, min)
+            .round() as u8
+    }
+}
+
+const fn full() -> f64 {
+    1.0
+}
+
+/// Keeps both edges within `[0, 1]` and `outer` at or past `inner`, since
+/// anything else would rescale by an empty or reversed range. A `NaN` edge
+/// falls back to its default.
+fn clamp_edges(inner: f64, outer: f64) -> (f64, f64) {
+    let inner = if inner.is_nan() {
+        0.0
+    } else {
+        inner.clamp(0.0, 1.0)
+    };
+    let outer = if outer.is_nan() {
+        full()
+    } else {
+        outer.clamp(inner, 1.0)
+    };
+
+    (inner, outer)
+}
+
+/// Maps `[inner, outer]` onto `[0, 1]`, saturating outside of it.
+fn rescale(value: f64, inner: f64, outer: f64) -> f64 {
+    if value <= inner {
+        0.0
+    } else if value >= outer {
+        1.0
+    } else {
+        (value - inner) / (outer - inner)
+    }
+}
+
+impl mapping::Layer for Deadzone {
+    fn name(&self) -> &'static str {
+        "Deadzoned"
+    }
+
+    fn apply(&mut self, input: Option<Input>) -> Option<Input> {
+        input.map(|input| Input {
+            main_stick: self.main_stick.apply(input.main_stick),
+            c_stick: self.c_stick.apply(input.c_stick),
+            left_trigger: self.left_trigger.apply(input.left_trigger),
+            right_trigger: self.right_trigger.apply(input.right_trigger),
+            ..input
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gcinput::Stick;
+
+    use super::{DeadzoneShape, StickDeadzone, TriggerDeadzone};
+    use crate::mapping::pipeline::DeadzoneConfig;
+
+    #[test]
+    fn deadzones_keep_the_full_range() {
+        let stick = |shape| StickDeadzone {
+            shape,
+            inner: 0.2,
+            outer: 0.9,
+        };
+        let drifting = Stick::new(0x80 + 0x10, 0x80 - 0x08);
+        let pushed = Stick::new(0xFF, 0x80);
+        let diagonal = Stick::new(0x80 + 0x40, 0x80 + 0x08);
+
+        for shape in enum_iterator::all::<DeadzoneShape>() {
+            assert_eq!(stick(shape).apply(drifting), Stick::new(0x80, 0x80));
+            assert_eq!(stick(shape).apply(pushed), Stick::new(0xFF, 0x80));
+            assert_eq!(stick(shape).apply(Stick::new(0x01, 0x80)).x, 0x01);
+        }
+
+        assert_eq!(stick(DeadzoneShape::Axial).apply(diagonal).y, 0x80);
+        assert!(stick(DeadzoneShape::ScaledRadial).apply(diagonal).y > 0x80);
+        assert!(
+            stick(DeadzoneShape::ScaledRadial).apply(diagonal).x
+                < stick(DeadzoneShape::Radial).apply(diagonal).x
+        );
+
+        let trigger = TriggerDeadzone {
+            inner: 0.1,
+            outer: 0.8,
+        };
+        assert_eq!(trigger.apply(0x10), 0x00);
+        assert_eq!(trigger.apply(0xD0), 0xFF);
+        assert!(trigger.apply(0x80) > 0x80);
+        assert_eq!(TriggerDeadzone::default().apply(0x42), 0x42);
+        assert_eq!(
+            StickDeadzone::default().apply(Stick::new(0x12, 0xEE)),
+            Stick::new(0x12, 0xEE)
+        );
+    }
+
+    #[test]
+    fn out_of_range_edges_are_clamped() {
+        let deadzone: DeadzoneConfig = toml::from_str(
+            r#"
+            main_stick = { shape = "scaled_radial", inner = -0.5, outer = 2.0 }
+            c_stick = { shape = "radial", outer = 0.0 }
+            left_trigger = { inner = 0.8, outer = 0.2 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(deadzone.main_stick.inner, 0.0);
+        assert_eq!(deadzone.main_stick.outer, 1.0);
+        assert_eq!(deadzone.left_trigger.outer, 0.8);
+
+        let pushed = Stick::new(0x80 + 0x20, 0x80);
+        assert_eq!(deadzone.main_stick.apply(pushed), pushed);
+        assert_eq!(deadzone.c_stick.apply(pushed), Stick::new(0xFF, 0x80));
+        assert_eq!(deadzone.left_trigger.apply(0x80), 0x00);
+        assert_eq!(deadzone.left_trigger.apply(0xF0), 0xFF);
+    }
+}
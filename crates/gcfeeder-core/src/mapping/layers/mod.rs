@@ -1,9 +1,19 @@
 mod analog_scaling;
+mod button_modes;
+mod button_remap;
 mod calibration;
 mod center_calibration;
+mod deadzone;
 mod ess_inversion;
+mod response_curve;
+mod routing;
 
 pub use analog_scaling::*;
+pub use button_modes::*;
+pub use button_remap::*;
 pub use calibration::*;
 pub use center_calibration::*;
+pub use deadzone::*;
 pub use ess_inversion::*;
+pub use response_curve::*;
+pub use routing::*;
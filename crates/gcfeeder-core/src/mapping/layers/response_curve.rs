@@ -0,0 +1,201 @@
+use std::iter;
+
+use gcinput::{Input, Stick, STICK_RANGE};
+use serde::{Deserialize, Serialize};
+
+use crate::mapping;
+
+/// Reshapes how far each stick reads as pushed, keeping the direction it is
+/// pushed in.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ResponseCurve {
+    pub main_stick: StickCurve,
+    pub c_stick: StickCurve,
+}
+
+/// Maps how far a stick is pushed, from 0 to 1, onto how far it reads as pushed.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum Curve {
+    #[default]
+    Linear,
+    Power {
+        exponent: f64,
+    },
+    /// Straight lines through `points` given in increasing order, between
+    /// `[0, 0]` and `[1, 1]`.
+    Piecewise {
+        points: Vec<[f64; 2]>,
+    },
+    /// A cubic Bézier from `[0, 0]` to `[1, 1]` with `points` as its control points.
+    Bezier {
+        points: [[f64; 2]; 2],
+    },
+}
+
+impl Curve {
+    pub fn eval(&self, m: f64) -> f64 {
+        if m <= 0.0 {
+            return 0.0;
+        } else if m >= 1.0 {
+            return 1.0;
+        }
+
+        let output = match self {
+            Self::Linear => m,
+            Self::Power { exponent } => m.powf(*exponent),
+            Self::Piecewise { points } => {
+                let mut previous = [0.0, 0.0];
+
+                for [x, y] in points.iter().copied().chain(iter::once([1.0, 1.0])) {
+                    if m <= x {
+                        let t = if x > previous[0] {
+                            (m - previous[0]) / (x - previous[0])
+                        } else {
+                            1.0
+                        };
+
+                        return t.mul_add(y - previous[1], previous[1]).clamp(0.0, 1.0);
+                    }
+
+                    previous = [x, y];
+                }
+
+                1.0
+            }
+            Self::Bezier { points: [p1, p2] } => {
+                let x1 = p1[0].clamp(0.0, 1.0);
+                let x2 = p2[0].clamp(0.0, 1.0);
+                let bezier = |t: f64, a: f64, b: f64| {
+                    let u = 1.0 - t;
+                    3.0 * u * u * t * a + 3.0 * u * t * t * b + t * t * t
+                };
+
+                // With the control points' x in range, x only grows with t.
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..32 {
+                    let t = 0.5 * (low + high);
+                    if bezier(t, x1, x2) < m {
+                        low = t;
+                    } else {
+                        high = t;
+                    }
+                }
+
+                bezier(0.5 * (low + high), p1[1], p2[1])
+            }
+        };
+
+        output.clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StickCurve {
+    pub curve: Curve,
+    /// The fraction of the radius a stick that is pushed at all reads as pushed
+    /// by, for games with large deadzones of their own.
+    pub anti_deadzone: f64,
+}
+
+impl StickCurve {
+    /// How far a stick pushed by `m` reads as pushed, both as fractions of the radius.
+    pub fn response(&self, m: f64) -> f64 {
+        if m <= 0.0 {
+            return 0.0;
+        }
+
+        let anti_deadzone = self.anti_deadzone.clamp(0.0, 1.0);
+        (1.0 - anti_deadzone).mul_add(self.curve.eval(m), anti_deadzone)
+    }
+
+    /// `samples + 1` evenly spaced points `[m, response(m)]` from 0 to 1, for plotting.
+    pub fn preview(&self, samples: usize) -> Vec<[f64; 2]> {
+        (0..=samples)
+            .map(|i| {
+                let m = i as f64 / samples.max(1) as f64;
+                [m, self.response(m)]
+            })
+            .collect()
+    }
+
+    pub fn apply(&self, stick: Stick) -> Stick {
+        let center = f64::from(STICK_RANGE.center);
+        let radius = f64::from(STICK_RANGE.radius);
+
+        let x = ((f64::from(stick.x) - center) / radius).clamp(-1.0, 1.0);
+        let y = ((f64::from(stick.y) - center) / radius).clamp(-1.0, 1.0);
+        // Past the rim only the gate's corners are left, which keep their place.
+        let magnitude = x.hypot(y).min(1.0);
+
+        if magnitude <= 0.0 {
+            return stick;
+        }
+
+        let scale = self.response(magnitude) / magnitude;
+        let denormalize = |n: f64| n.clamp(-1.0, 1.0).mul_add(radius, center).round() as u8;
+        Stick::new(denormalize(x * scale), denormalize(y * scale))
+    }
+}
+
+impl mapping::Layer for ResponseCurve {
+    fn name(&self) -> &'static str {
+        "Curved"
+    }
+
+    fn apply(&mut self, input: Option<Input>) -> Option<Input> {
+        input.map(|input| Input {
+            main_stick: self.main_stick.apply(input.main_stick),
+            c_stick: self.c_stick.apply(input.c_stick),
+            ..input
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gcinput::Stick;
+
+    use super::{Curve, StickCurve};
+    use crate::mapping::pipeline::ResponseCurveConfig;
+
+    #[test]
+    fn curves_keep_the_angle_and_ends() {
+        let curves: ResponseCurveConfig = toml::from_str(
+            r#"
+            main_stick = { curve = { shape = "power", exponent = 2.0 }, anti_deadzone = 0.2 }
+            c_stick = { curve = { shape = "piecewise", points = [[0.5, 0.25]] } }
+            "#,
+        )
+        .unwrap();
+        let bezier = Curve::Bezier {
+            points: [[0.5, 0.0], [0.5, 1.0]],
+        };
+
+        for curve in [&curves.main_stick.curve, &curves.c_stick.curve, &bezier] {
+            assert_eq!(curve.eval(0.0), 0.0);
+            assert!((curve.eval(1.0) - 1.0).abs() < 1e-6);
+            assert!(curve.eval(0.25) < 0.25);
+        }
+        assert!((bezier.eval(0.5) - 0.5).abs() < 1e-6);
+        assert_eq!(curves.c_stick.curve.eval(0.75), 0.625);
+
+        let main = &curves.main_stick;
+        assert_eq!(main.response(0.0), 0.0);
+        assert!((main.response(0.5) - 0.4).abs() < 1e-9);
+        assert_eq!(main.preview(4).len(), 5);
+        assert_eq!(main.preview(4)[4], [1.0, 1.0]);
+
+        let halfway = main.apply(Stick::new(0x80 + 0x40, 0x80 - 0x40));
+        assert_eq!(halfway.x - 0x80, 0x80 - halfway.y);
+        assert_eq!(main.apply(Stick::new(0x80, 0x80)), Stick::new(0x80, 0x80));
+        assert_eq!(main.apply(Stick::new(0xFF, 0x80)), Stick::new(0xFF, 0x80));
+
+        let identity = StickCurve::default();
+        assert_eq!(
+            identity.apply(Stick::new(0x12, 0xEE)),
+            Stick::new(0x12, 0xEE)
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use gcinput::{Button, Input, STICK_RANGE, TRIGGER_RANGE};
+use serde::{Deserialize, Serialize};
+
+use crate::mapping;
+
+/// An analog channel, read as -1 to 1 for stick axes and 0 to 1 for triggers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+    MainX,
+    MainY,
+    CX,
+    CY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl Axis {
+    pub fn read(self, input: &Input) -> f64 {
+        let stick = |n: u8| {
+            ((f64::from(n) - f64::from(STICK_RANGE.center)) / f64::from(STICK_RANGE.radius))
+                .clamp(-1.0, 1.0)
+        };
+        let trigger = |n: u8| {
+            (f64::from(n) - f64::from(TRIGGER_RANGE.min))
+                / f64::from(TRIGGER_RANGE.max - TRIGGER_RANGE.min)
+        };
+
+        match self {
+            Self::MainX => stick(input.main_stick.x),
+            Self::MainY => stick(input.main_stick.y),
+            Self::CX => stick(input.c_stick.x),
+            Self::CY => stick(input.c_stick.y),
+            Self::LeftTrigger => trigger(input.left_trigger),
+            Self::RightTrigger => trigger(input.right_trigger),
+        }
+    }
+
+    /// Writes `value`, clamped to the axis' range.
+    pub fn write(self, input: &mut Input, value: f64) {
+        let stick = |v: f64| {
+            v.clamp(-1.0, 1.0)
+                .mul_add(f64::from(STICK_RANGE.radius), f64::from(STICK_RANGE.center))
+                .round() as u8
+        };
+        let trigger = |v: f64| {
+            v.clamp(0.0, 1.0)
+                .mul_add(
+                    f64::from(TRIGGER_RANGE.max - TRIGGER_RANGE.min),
+                    f64::from(TRIGGER_RANGE.min),
+                )
+                .round() as u8
+        };
+
+        match self {
+            Self::MainX => input.main_stick.x = stick(value),
+            Self::MainY => input.main_stick.y = stick(value),
+            Self::CX => input.c_stick.x = stick(value),
+            Self::CY => input.c_stick.y = stick(value),
+            Self::LeftTrigger => input.left_trigger = trigger(value),
+            Self::RightTrigger => input.right_trigger = trigger(value),
+        }
+    }
+}
+
+/// Written as the name of an axis or a button.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Channel {
+    Analog(Axis),
+    Digital(Button),
+}
+
+impl Channel {
+    fn read(self, input: &Input) -> f64 {
+        match self {
+            Self::Analog(axis) => axis.read(input),
+            Self::Digital(button) => f64::from(u8::from(input.button(button))),
+        }
+    }
+
+    fn reset(self, input: &mut Input) {
+        match self {
+            Self::Analog(axis) => axis.write(input, 0.0),
+            Self::Digital(button) => input.set_button(button, false),
+        }
+    }
+}
+
+/// Sends `from` to `to`, which leaves `from` neutral unless `keep` is set or
+/// another route sends something to it.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Route {
+    pub from: Channel,
+    pub to: Channel,
+    /// Multiplies the source's value, which inverts it when negative.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// The scaled value past which a digital channel is pressed.
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+    /// How far below `threshold` the value has to fall to release the press.
+    #[serde(default)]
+    pub hysteresis: f64,
+    /// Whether `from` keeps its own value too.
+    #[serde(default)]
+    pub keep: bool,
+}
+
+const fn default_scale() -> f64 {
+    1.0
+}
+
+const fn default_threshold() -> f64 {
+    0.5
+}
+
+/// Routes channels of the input to other channels, converting between analog
+/// and digital ones.
+///
+/// Buttons routed to an axis only move it while pressed, so a route per
+/// direction can share an axis.
+pub struct Routing {
+    routes: Vec<Route>,
+    /// Whether each route's digital output is pressed.
+    pressed: Vec<bool>,
+}
+
+impl Routing {
+    pub fn new(routes: Vec<Route>) -> Self {
+        Self {
+            pressed: vec![false; routes.len()],
+            routes,
+        }
+    }
+
+    pub fn route(&mut self, input: Input) -> Input {
+        let mut routed = input;
+
+        for route in self.routes.iter().filter(|r| !r.keep) {
+            route.from.reset(&mut routed);
+        }
+
+        for (route, pressed) in self.routes.iter().zip(self.pressed.iter_mut()) {
+            let value = route.from.read(&input) * route.scale;
+
+            match (route.from, route.to) {
+                (_, Channel::Digital(button)) => {
+                    let release_at = route.threshold - route.hysteresis;
+                    *pressed = value >= route.threshold || (*pressed && value > release_at);
+
+                    if *pressed {
+                        routed.set_button(button, true);
+                    }
+                }
+                (Channel::Digital(button), Channel::Analog(axis)) => {
+                    if input.button(button) {
+                        axis.write(&mut routed, value);
+                    }
+                }
+                (Channel::Analog(_), Channel::Analog(axis)) => axis.write(&mut routed, value),
+            }
+        }
+
+        routed
+    }
+}
+
+impl mapping::Layer for Routing {
+    fn name(&self) -> &'static str {
+        "Routed"
+    }
+
+    fn apply(&mut self, input: Option<Input>) -> Option<Input> {
+        match input {
+            Some(input) => Some(self.route(input)),
+            None => {
+                self.pressed.fill(false);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gcinput::{Input, Stick};
+
+    use super::Routing;
+    use crate::mapping::pipeline::RoutingConfig;
+
+    #[test]
+    fn routes_convert_between_channels() {
+        let config: RoutingConfig = toml::from_str(
+            r#"
+            routes = [
+                { from = "c_x", to = "right" },
+                { from = "c_x", to = "left", scale = -1.0 },
+                { from = "left_trigger", to = "l", threshold = 0.8, hysteresis = 0.2 },
+                { from = "up", to = "main_y" },
+                { from = "main_x", to = "main_x", scale = -1.0 },
+            ]
+            "#,
+        )
+        .unwrap();
+        let round_trip: RoutingConfig = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(round_trip.routes, config.routes);
+
+        let mut routing = Routing::new(config.routes);
+
+        let mut input = Input {
+            main_stick: Stick::new(0xC0, 0x90),
+            c_stick: Stick::new(0x10, 0x80),
+            left_trigger: 0xF0,
+            ..Default::default()
+        };
+        let routed = routing.route(input);
+        assert!(routed.button_left && !routed.button_right);
+        assert_eq!(routed.c_stick, Stick::new(0x80, 0x80));
+        assert!(routed.button_l);
+        assert_eq!(routed.left_trigger, 0x00);
+        assert_eq!(routed.main_stick, Stick::new(0x40, 0x90));
+
+        input.left_trigger = 0xA0;
+        input.button_up = true;
+        let routed = routing.route(input);
+        assert!(routed.button_l);
+        assert!(!routed.button_up);
+        assert_eq!(routed.main_stick.y, 0xFF);
+
+        input.left_trigger = 0x90;
+        assert!(!routing.route(input).button_l);
+    }
+}
@@ -1,6 +1,7 @@
 use gcinput::Input;
 
 pub mod layers;
+pub mod pipeline;
 
 pub trait Layer {
     fn name(&self) -> &'static str;
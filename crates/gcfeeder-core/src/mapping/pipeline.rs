@@ -0,0 +1,442 @@
+//! Builds a profile's ordered list of layers through a registry of layer kinds.
+//!
+//! Each entry of a profile's `layers` is a table whose `type` names a registered
+//! kind and whose other keys are that kind's parameters. Crates embedding the
+//! feeder can add kinds with [`register`].
+
+use std::{
+    collections::BTreeMap,
+    sync::{PoisonError, RwLock},
+};
+
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, ser::SerializeMap, Deserialize, Deserializer, Serialize};
+use toml::value::{Table, Value};
+
+use gcinput::Button;
+
+use super::layers::{
+    AnalogScaling, ButtonModes, ButtonRemap, Calibration, CenterCalibration, Deadzone,
+    EssInversion, Mode, ResponseCurve, Route, Routing, StickCurve, StickDeadzone, Targets,
+    TriggerDeadzone,
+};
+use crate::feeder::{self, Layer};
+
+const KIND_KEY: &str = "type";
+
+type Build = Box<dyn Fn(Value, &feeder::Config) -> Result<Option<Box<Layer>>> + Send + Sync>;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+static REGISTRY: Lazy<RwLock<BTreeMap<&'static str, Build>>> = Lazy::new(|| {
+    let mut registry = BTreeMap::new();
+    insert::<CenterCalibrationConfig>(&mut registry);
+    insert::<AnalogScalingConfig>(&mut registry);
+    insert::<EssInversionConfig>(&mut registry);
+    insert::<CalibrationConfig>(&mut registry);
+    insert::<DeadzoneConfig>(&mut registry);
+    insert::<ButtonRemapConfig>(&mut registry);
+    insert::<RoutingConfig>(&mut registry);
+    insert::<ButtonModesConfig>(&mut registry);
+    insert::<ResponseCurveConfig>(&mut registry);
+    RwLock::new(registry)
+});
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("unknown layer type \"{0}\"")]
+    UnknownKind(String),
+    #[error("invalid parameters for layer type \"{kind}\": {source}")]
+    Params {
+        kind: String,
+        source: toml::de::Error,
+    },
+}
+
+/// The parameters of a kind of layer that profiles can list.
+pub trait LayerConfig: DeserializeOwned {
+    /// The `type` the layer is listed under.
+    const KIND: &'static str;
+
+    /// Builds the layer, or nothing if it would leave every input as is.
+    ///
+    /// `profile` holds settings that are edited outside of the layer list, like
+    /// calibration data.
+    fn build(self, profile: &feeder::Config) -> Option<Box<Layer>>;
+}
+
+/// Adds a kind of layer that profiles can list, replacing any of the same kind.
+pub fn register<C: LayerConfig>() {
+    let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    insert::<C>(&mut registry);
+}
+
+/// The kinds of layer that can be listed.
+pub fn kinds() -> Vec<&'static str> {
+    let registry = REGISTRY.read().unwrap_or_else(PoisonError::into_inner);
+    registry.keys().copied().collect()
+}
+
+fn insert<C: LayerConfig>(registry: &mut BTreeMap<&'static str, Build>) {
+    registry.insert(
+        C::KIND,
+        Box::new(|params, profile| {
+            let config = C::deserialize(params).map_err(|source| Error::Params {
+                kind: C::KIND.to_owned(),
+                source,
+            })?;
+
+            Ok(config.build(profile))
+        }),
+    );
+}
+
+/// An entry of a profile's layer list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerSpec {
+    pub kind: String,
+    pub params: Table,
+}
+
+impl LayerSpec {
+    pub fn new(kind: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            params: Table::new(),
+        }
+    }
+
+    pub fn build(&self, profile: &feeder::Config) -> Result<Option<Box<Layer>>> {
+        let registry = REGISTRY.read().unwrap_or_else(PoisonError::into_inner);
+        let build = registry
+            .get(self.kind.as_str())
+            .ok_or_else(|| Error::UnknownKind(self.kind.clone()))?;
+
+        build(Value::Table(self.params.clone()), profile)
+    }
+}
+
+impl Serialize for LayerSpec {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.params.len() + 1))?;
+        map.serialize_entry(KIND_KEY, &self.kind)?;
+        for (key, value) in self.params.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for LayerSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let mut params = Table::deserialize(deserializer)?;
+
+        match params.remove(KIND_KEY) {
+            Some(Value::String(kind)) => Ok(Self { kind, params }),
+            _ => Err(serde::de::Error::missing_field(KIND_KEY)),
+        }
+    }
+}
+
+/// The layers of profiles that predate the layer list, in their old order.
+pub fn default_layers() -> Vec<LayerSpec> {
+    [
+        CenterCalibrationConfig::KIND,
+        AnalogScalingConfig::KIND,
+        EssInversionConfig::KIND,
+        CalibrationConfig::KIND,
+    ]
+    .into_iter()
+    .map(LayerSpec::new)
+    .collect()
+}
+
+/// Treats where the sticks and triggers rest when the controller connects as
+/// their center.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct CenterCalibrationConfig {}
+
+impl LayerConfig for CenterCalibrationConfig {
+    const KIND: &'static str = "center_calibration";
+
+    fn build(self, _profile: &feeder::Config) -> Option<Box<Layer>> {
+        Some(Box::<CenterCalibration>::default())
+    }
+}
+
+/// Scales the sticks around their center, by the profile's analog scale
+/// unless one is given.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct AnalogScalingConfig {
+    pub scale: Option<f64>,
+}
+
+impl LayerConfig for AnalogScalingConfig {
+    const KIND: &'static str = "analog_scaling";
+
+    fn build(self, profile: &feeder::Config) -> Option<Box<Layer>> {
+        let scale = self.scale.unwrap_or(profile.analog_scale);
+        ((scale.abs() - 1.0).abs() >= 1e-10).then(|| Box::new(AnalogScaling::new(scale)) as _)
+    }
+}
+
+/// Inverts an ESS adapter's mapping, by the profile's ESS mapping unless one
+/// is given.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct EssInversionConfig {
+    pub mapping: Option<EssInversion>,
+}
+
+impl LayerConfig for EssInversionConfig {
+    const KIND: &'static str = "ess_inversion";
+
+    fn build(self, profile: &feeder::Config) -> Option<Box<Layer>> {
+        let mapping = self.mapping.or(profile.ess.inversion_mapping)?;
+        Some(Box::new(mapping))
+    }
+}
+
+/// Applies the profile's calibration when it is enabled.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct CalibrationConfig {}
+
+impl LayerConfig for CalibrationConfig {
+    const KIND: &'static str = "calibration";
+
+    fn build(self, profile: &feeder::Config) -> Option<Box<Layer>> {
+        let calibration = &profile.calibration;
+
+        calibration.enabled.then(|| {
+            Box::new(Calibration::new(
+                calibration.stick_data,
+                calibration.trigger_data,
+            )) as _
+        })
+    }
+}
+
+/// Deadzones for each stick and trigger, none by default.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeadzoneConfig {
+    pub main_stick: StickDeadzone,
+    pub c_stick: StickDeadzone,
+    pub left_trigger: TriggerDeadzone,
+    pub right_trigger: TriggerDeadzone,
+}
+
+impl LayerConfig for DeadzoneConfig {
+    const KIND: &'static str = "deadzone";
+
+    fn build(self, _profile: &feeder::Config) -> Option<Box<Layer>> {
+        (self != Self::default()).then(|| {
+            Box::new(Deadzone {
+                main_stick: self.main_stick,
+                c_stick: self.c_stick,
+                left_trigger: self.left_trigger,
+                right_trigger: self.right_trigger,
+            }) as _
+        })
+    }
+}
+
+/// The buttons each listed button presses instead of itself.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ButtonRemapConfig {
+    #[serde(with = "button_keys")]
+    pub map: BTreeMap<Button, Targets>,
+}
+
+impl LayerConfig for ButtonRemapConfig {
+    const KIND: &'static str = "button_remap";
+
+    fn build(self, _profile: &feeder::Config) -> Option<Box<Layer>> {
+        let remap = ButtonRemap::new(self.map);
+        (!remap.is_identity()).then(|| Box::new(remap) as _)
+    }
+}
+
+/// A response curve for each stick, linear by default.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseCurveConfig {
+    pub main_stick: StickCurve,
+    pub c_stick: StickCurve,
+}
+
+impl LayerConfig for ResponseCurveConfig {
+    const KIND: &'static str = "response_curve";
+
+    fn build(self, _profile: &feeder::Config) -> Option<Box<Layer>> {
+        (self != Self::default()).then(|| {
+            Box::new(ResponseCurve {
+                main_stick: self.main_stick,
+                c_stick: self.c_stick,
+            }) as _
+        })
+    }
+}
+
+/// The mode of each listed button, which other buttons do not have.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ButtonModesConfig {
+    #[serde(with = "button_keys")]
+    pub modes: BTreeMap<Button, Mode>,
+}
+
+impl LayerConfig for ButtonModesConfig {
+    const KIND: &'static str = "button_modes";
+
+    fn build(self, _profile: &feeder::Config) -> Option<Box<Layer>> {
+        (!self.modes.is_empty()).then(|| Box::new(ButtonModes::new(self.modes)) as _)
+    }
+}
+
+/// Routes analog and digital channels to other channels, in order.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    #[serde(default)]
+    pub routes: Vec<Route>,
+}
+
+impl LayerConfig for RoutingConfig {
+    const KIND: &'static str = "routing";
+
+    fn build(self, _profile: &feeder::Config) -> Option<Box<Layer>> {
+        (!self.routes.is_empty()).then(|| Box::new(Routing::new(self.routes)) as _)
+    }
+}
+
+/// Reads and writes maps keyed by buttons under the buttons' names, since
+/// TOML keys are always strings, which serde will not treat as an enum directly.
+mod button_keys {
+    use std::collections::BTreeMap;
+
+    use gcinput::Button;
+    use serde::{
+        de::IntoDeserializer, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    pub fn serialize<S: Serializer, V: Serialize>(
+        map: &BTreeMap<Button, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let named = map
+            .iter()
+            .map(|(&button, value)| match toml::Value::try_from(button) {
+                Ok(toml::Value::String(name)) => Ok((name, value)),
+                _ => Err(S::Error::custom(format!("{:?} has no name", button))),
+            })
+            .collect::<Result<BTreeMap<_, _>, S::Error>>()?;
+
+        named.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Button, V>, D::Error> {
+        BTreeMap::<String, V>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| {
+                let button = Button::deserialize(key.as_str().into_deserializer())?;
+                Ok((button, value))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gcinput::{Input, Stick};
+    use serde::Deserialize;
+
+    use super::{kinds, register, LayerConfig, LayerSpec};
+    use crate::feeder::{self, Layer};
+
+    struct Invert;
+
+    impl crate::mapping::Layer for Invert {
+        fn name(&self) -> &'static str {
+            "Inverted"
+        }
+
+        fn apply(&mut self, input: Option<Input>) -> Option<Input> {
+            input.map(|input| Input {
+                main_stick: input.main_stick.map(|n| u8::MAX - n),
+                ..input
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct InvertConfig {
+        enabled: bool,
+    }
+
+    impl LayerConfig for InvertConfig {
+        const KIND: &'static str = "test_invert";
+
+        fn build(self, _profile: &feeder::Config) -> Option<Box<Layer>> {
+            self.enabled.then(|| Box::new(Invert) as _)
+        }
+    }
+
+    #[test]
+    fn layer_specs_build_through_the_registry() {
+        let specs: Vec<LayerSpec> = toml::from_str::<toml::value::Table>(
+            r#"
+            layers = [
+                { type = "analog_scaling", scale = 0.5 },
+                { type = "test_invert", enabled = true },
+            ]
+            "#,
+        )
+        .unwrap()["layers"]
+            .clone()
+            .try_into()
+            .unwrap();
+
+        let profile = feeder::Config::default();
+        assert!(specs[1].build(&profile).is_err());
+
+        register::<InvertConfig>();
+        assert!(kinds().contains(&"test_invert"));
+
+        let mut layers = specs
+            .iter()
+            .map(|spec| spec.build(&profile).unwrap().unwrap())
+            .collect::<Vec<_>>();
+        let input = Input {
+            main_stick: Stick::new(0xFF, 0x80),
+            ..Default::default()
+        };
+        let mut output = Some(input);
+        for layer in layers.iter_mut() {
+            output = layer.apply(output);
+        }
+        assert_eq!(output.unwrap().main_stick, Stick::new(0x40, 0x7F));
+
+        let bad = LayerSpec {
+            params: [("enabled".to_owned(), toml::Value::from(1))]
+                .into_iter()
+                .collect(),
+            ..LayerSpec::new("test_invert")
+        };
+        assert!(bad.build(&profile).is_err());
+        assert!(LayerSpec::new("analog_scaling")
+            .build(&profile)
+            .unwrap()
+            .is_none());
+
+        let round_trip: LayerSpec = toml::Value::try_from(&specs[0])
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(round_trip, specs[0]);
+    }
+}
@@ -0,0 +1,201 @@
+//! Latency statistics that take the same memory no matter how many samples
+//! are recorded.
+
+use std::time::Duration;
+
+/// Each power of two microseconds is split into this many buckets, which keeps
+/// every reported percentile within about 6% of the true value.
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+/// Samples of `2^MAX_BITS` microseconds (about 16 seconds) or more share the last bucket.
+const MAX_BITS: u32 = 24;
+const BUCKETS: usize = ((MAX_BITS - SUB_BUCKET_BITS + 1) as u64 * SUB_BUCKETS) as usize;
+
+/// A histogram of durations with microsecond resolution.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    counts: Box<[u64; BUCKETS]>,
+    count: u64,
+    min: Duration,
+    max: Duration,
+    last: Option<Duration>,
+    /// Smoothed difference between consecutive samples, in seconds.
+    jitter: f64,
+    budget: Duration,
+    overruns: u64,
+}
+
+impl Histogram {
+    /// Creates an empty histogram that counts samples longer than `budget` as overruns.
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            counts: Box::new([0; BUCKETS]),
+            count: 0,
+            min: Duration::MAX,
+            max: Duration::ZERO,
+            last: None,
+            jitter: 0.0,
+            budget,
+            overruns: 0,
+        }
+    }
+
+    pub fn record(&mut self, sample: Duration) {
+        self.counts[bucket(sample)] += 1;
+        self.count += 1;
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+
+        if let Some(last) = self.last {
+            // Smoothed like RTP's interarrival jitter (RFC 3550).
+            let difference = sample.as_secs_f64() - last.as_secs_f64();
+            self.jitter += (difference.abs() - self.jitter) / 16.0;
+        }
+        self.last = Some(sample);
+
+        if sample > self.budget {
+            self.overruns += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new(self.budget);
+    }
+
+    /// Counts samples recorded from now on as overruns if they are longer than `budget`.
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
+    }
+
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The smallest duration that `quantile` of the samples are at most, which
+    /// is `None` if nothing was recorded.
+    #[must_use]
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        let index = self
+            .counts
+            .iter()
+            .position(|&count| {
+                seen += count;
+                seen >= rank
+            })
+            .unwrap_or(BUCKETS - 1);
+
+        Some(bucket_upper_bound(index).clamp(self.min, self.max))
+    }
+
+    #[must_use]
+    pub fn summary(&self) -> Option<Summary> {
+        Some(Summary {
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            p50: self.quantile(0.5)?,
+            p99: self.quantile(0.99)?,
+            p999: self.quantile(0.999)?,
+            jitter: Duration::from_secs_f64(self.jitter),
+            overruns: self.overruns,
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub count: u64,
+    pub min: Duration,
+    pub max: Duration,
+    pub p50: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+    pub jitter: Duration,
+    /// How many samples were longer than the histogram's budget.
+    pub overruns: u64,
+}
+
+fn bucket(sample: Duration) -> usize {
+    let micros = u64::try_from(sample.as_micros())
+        .unwrap_or(u64::MAX)
+        .min((1 << MAX_BITS) - 1);
+
+    if micros < 2 * SUB_BUCKETS {
+        return micros as usize;
+    }
+
+    let shift = u64::BITS - micros.leading_zeros() - (SUB_BUCKET_BITS + 1);
+    ((u64::from(shift) + 1) * SUB_BUCKETS + ((micros >> shift) - SUB_BUCKETS)) as usize
+}
+
+fn bucket_upper_bound(index: usize) -> Duration {
+    let index = index as u64;
+
+    if index < 2 * SUB_BUCKETS {
+        return Duration::from_micros(index);
+    }
+
+    let shift = index / SUB_BUCKETS - 1;
+    let low = (index % SUB_BUCKETS + SUB_BUCKETS) << shift;
+    Duration::from_micros(low + (1 << shift) - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{bucket, bucket_upper_bound, Histogram, BUCKETS};
+
+    #[test]
+    fn buckets_cover_every_duration() {
+        for micros in [0, 1, 31, 32, 33, 100, 8_000, 123_456, (1 << 24) - 1] {
+            let sample = Duration::from_micros(micros);
+            let index = bucket(sample);
+
+            assert!(bucket_upper_bound(index) >= sample);
+            assert!(index == 0 || bucket_upper_bound(index - 1) < sample);
+        }
+
+        assert_eq!(bucket(Duration::MAX), BUCKETS - 1);
+    }
+
+    #[test]
+    fn histogram_reports_spikes() {
+        let mut histogram = Histogram::new(Duration::from_millis(8));
+        assert_eq!(histogram.summary(), None);
+
+        for _ in 0..990 {
+            histogram.record(Duration::from_millis(1));
+        }
+        for _ in 0..10 {
+            histogram.record(Duration::from_millis(20));
+        }
+
+        let summary = histogram.summary().unwrap();
+        assert_eq!(summary.count, 1000);
+        assert_eq!(summary.min, Duration::from_millis(1));
+        assert_eq!(summary.max, Duration::from_millis(20));
+        assert!(
+            summary.p50 >= Duration::from_millis(1) && summary.p50 < Duration::from_micros(1100)
+        );
+        assert!(summary.p99 < Duration::from_micros(1100));
+        assert!(summary.p999 >= Duration::from_millis(19));
+        assert_eq!(summary.overruns, 10);
+        assert!(summary.jitter > Duration::ZERO);
+
+        histogram.set_budget(Duration::from_micros(500));
+        histogram.record(Duration::from_millis(1));
+        assert_eq!(histogram.summary().unwrap().overruns, 11);
+
+        histogram.clear();
+        assert_eq!(histogram.count(), 0);
+    }
+}
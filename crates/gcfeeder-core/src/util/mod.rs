@@ -1,6 +1,7 @@
 pub use average_timer::AverageTimer;
 
 pub mod average_timer;
+pub mod metrics;
 pub mod recent_channel;
 
 macro_rules! packed_bools {
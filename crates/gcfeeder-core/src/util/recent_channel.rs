@@ -1,9 +1,9 @@
 use std::{
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
-use crossbeam::{atomic::AtomicCell, channel};
+use crossbeam::channel;
 
 pub type SendError<T> = channel::SendError<T>;
 pub type SendTimeoutError<T> = channel::SendTimeoutError<T>;
@@ -12,8 +12,8 @@ pub type RecvError = channel::RecvError;
 pub type RecvTimeoutError = channel::RecvTimeoutError;
 pub type TryRecvError = channel::TryRecvError;
 
-pub fn channel<T: Copy>() -> (Sender<T>, Receiver<T>) {
-    let msg = Arc::new(AtomicCell::new(None));
+pub fn channel<T: Clone>() -> (Sender<T>, Receiver<T>) {
+    let msg = Arc::new(Mutex::new(None));
     let (sender, receiver) = channel::bounded(1);
 
     (
@@ -25,19 +25,19 @@ pub fn channel<T: Copy>() -> (Sender<T>, Receiver<T>) {
     )
 }
 
-pub struct Sender<T: Copy> {
+pub struct Sender<T: Clone> {
     sender: channel::Sender<()>,
-    msg: Arc<AtomicCell<Option<T>>>,
+    msg: Arc<Mutex<Option<T>>>,
 }
 
-impl<T: Copy> Sender<T> {
+impl<T: Clone> Sender<T> {
     pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
-        self.msg.store(Some(msg));
+        self.store(&msg);
         self.sender.send(()).map_err(|_| channel::SendError(msg))
     }
 
     pub fn send_deadline(&self, msg: T, deadline: Instant) -> Result<(), SendTimeoutError<T>> {
-        self.msg.store(Some(msg));
+        self.store(&msg);
         match self.sender.send_deadline((), deadline) {
             Ok(msg) => Ok(msg),
             Err(e) => self.map_timeout(msg, e),
@@ -45,7 +45,7 @@ impl<T: Copy> Sender<T> {
     }
 
     pub fn send_timeout(&self, msg: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
-        self.msg.store(Some(msg));
+        self.store(&msg);
         match self.sender.send_timeout((), timeout) {
             Ok(msg) => Ok(msg),
             Err(e) => self.map_timeout(msg, e),
@@ -53,13 +53,17 @@ impl<T: Copy> Sender<T> {
     }
 
     pub fn try_send(&self, msg: T) -> Result<(), TrySendError<T>> {
-        self.msg.store(Some(msg));
+        self.store(&msg);
         match self.sender.try_send(()) {
             Ok(msg) => Ok(msg),
             Err(e) => self.map_try(msg, e),
         }
     }
 
+    fn store(&self, msg: &T) {
+        *self.msg.lock().unwrap() = Some(msg.clone());
+    }
+
     fn map_timeout(
         &self,
         msg: T,
@@ -79,12 +83,12 @@ impl<T: Copy> Sender<T> {
     }
 }
 
-pub struct Receiver<T: Copy> {
+pub struct Receiver<T: Clone> {
     receiver: channel::Receiver<()>,
-    msg: Arc<AtomicCell<Option<T>>>,
+    msg: Arc<Mutex<Option<T>>>,
 }
 
-impl<T: Copy> Receiver<T> {
+impl<T: Clone> Receiver<T> {
     pub fn recv(&self) -> Result<T, RecvError> {
         self.receiver.recv().map(|()| self.msg())
     }
@@ -103,7 +107,7 @@ impl<T: Copy> Receiver<T> {
 
     #[inline(always)]
     fn msg(&self) -> T {
-        self.msg.load().unwrap()
+        self.msg.lock().unwrap().clone().unwrap()
     }
 }
 
@@ -2,7 +2,11 @@ use std::{array, collections::HashMap};
 
 use serde::{Deserialize, Serialize};
 
-use gcfeeder_core::{adapter::Port, feeder};
+use gcfeeder_core::{
+    adapter::{quirks::Family, Port},
+    feeder,
+    hotkey::Hotkey,
+};
 
 pub type Profile = feeder::Config;
 
@@ -10,17 +14,24 @@ pub const DEFAULT_PROFILE_NAME: &str = "default";
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Checked on every port, so they work the same in any profile.
+    #[serde(default)]
+    pub hotkeys: Vec<Hotkey>,
     pub profile: ProfileConfig,
     pub input_server: [InputServerConfig; Port::COUNT],
+    #[serde(default)]
+    pub adapter: AdapterConfig,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            hotkeys: Vec::new(),
             profile: Default::default(),
             input_server: array::from_fn(|i| {
                 InputServerConfig::new_disabled(4096 + u16::try_from(i).unwrap())
             }),
+            adapter: Default::default(),
         }
     }
 }
@@ -57,7 +68,7 @@ impl Default for ProfileConfig {
     }
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct InputServerConfig {
     pub enabled: bool,
     pub port: u16,
@@ -71,3 +82,9 @@ impl InputServerConfig {
         }
     }
 }
+
+#[derive(Copy, Clone, Default, Serialize, Deserialize)]
+pub struct AdapterConfig {
+    /// Quirks to use for every adapter instead of detecting them.
+    pub quirks: Option<Family>,
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs,
     io::{self, Read, Write},
     net::UdpSocket,
@@ -9,6 +9,7 @@ use std::{
 
 use eframe::egui;
 use enum_iterator::all;
+#[cfg(windows)]
 use trayicon::TrayIcon;
 
 use self::panel::calibration::ConfigUpdate;
@@ -17,23 +18,30 @@ use super::log::Message as LogMessage;
 use crate::config::{Config, Profile};
 use crossbeam::channel;
 use gcfeeder_core::{
-    adapter::{poller::Poller, Port},
+    adapter::{
+        self,
+        poller::{ConnectionEvent, Poller},
+        transport::{UsbBus, UsbTransport},
+        AdapterId, ControllerId, Port,
+    },
     feeder::{self, Feeder, Record},
+    hotkey,
     util::recent_channel::{self as recent, TryRecvError},
 };
 use log::{info, warn};
 use panel::{
     calibration::State as CalibrationState, config::Message as ConfigMessage,
     config::State as ConfigState, profile::Message as ProfileMessage,
-    profile::State as ProfileState, CalibrationPanel, ConfigEditor, LogPanel, ProfilePanel,
-    StatsPanel,
+    profile::State as ProfileState, trace::State as TraceState, CalibrationPanel, ConfigEditor,
+    LogPanel, ProfilePanel, StatsPanel, TracePanel,
 };
 
 mod panel;
 mod widget;
 
-type Usb = rusb::GlobalContext;
+type Usb = UsbTransport<rusb::GlobalContext>;
 
+#[cfg(windows)]
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum TrayMessage {
     Show,
@@ -48,48 +56,84 @@ pub struct App {
     profile_state: Option<ProfileState>,
     editor_profile: Option<String>,
     stats_open: bool,
+    trace_state: Option<TraceState>,
+    trace_open: bool,
     config: Config,
     config_path: PathBuf,
+    #[cfg(windows)]
     _tray_icon: TrayIcon<TrayMessage>,
+    #[cfg(windows)]
     tray_receiver: channel::Receiver<TrayMessage>,
     hidden: bool,
     poller: Poller<Usb>,
-    feeders: [Feeder<Usb>; Port::COUNT],
-    receivers: [feeder::Receiver; Port::COUNT],
-    records: [Option<Record>; Port::COUNT],
+    connection_events: channel::Receiver<ConnectionEvent>,
+    hotkey_sender: channel::Sender<hotkey::Event>,
+    hotkey_events: channel::Receiver<hotkey::Event>,
+    adapter_errors: BTreeMap<AdapterId, adapter::Error>,
+    captures: Vec<AdapterId>,
+    /// The connected adapters in the order they attached. The first one serves
+    /// the input servers.
+    adapters: Vec<AdapterId>,
+    slots: BTreeMap<ControllerId, Slot>,
+}
+
+/// The feeder of one port of one adapter, which uses the profile selected for the port.
+pub struct Slot {
+    pub feeder: Feeder<Usb>,
+    receiver: feeder::Receiver,
+    pub record: Option<Record>,
 }
 
 impl App {
     const CONFIG_PATH: &'static str = "gcfeeder.toml";
 
     pub fn new(
-        tray_icon: TrayIcon<TrayMessage>,
-        tray_receiver: channel::Receiver<TrayMessage>,
+        #[cfg(windows)] tray_icon: TrayIcon<TrayMessage>,
+        #[cfg(windows)] tray_receiver: channel::Receiver<TrayMessage>,
         log_receiver: channel::Receiver<LogMessage>,
     ) -> Self {
         let config_path = Path::new(Self::CONFIG_PATH).to_path_buf();
 
         let config = Self::load_or_create_config(&config_path);
-        let poller = Poller::new(Usb {});
-        let (feeders, receivers) = Self::feeders_from_config(&config, &poller);
-
-        Self {
+        let poller = Poller::<Usb>::new(UsbBus::new(rusb::GlobalContext {}));
+        poller
+            .transport_context()
+            .set_quirks_override(config.adapter.quirks);
+        let connection_events = poller.connection_events();
+        let (hotkey_sender, hotkey_events) = channel::unbounded();
+
+        let mut app = Self {
             log_panel: LogPanel::new(log_receiver),
             calibration_state: None,
             config_state: None,
             profile_state: None,
             editor_profile: None,
             stats_open: false,
+            trace_state: None,
+            trace_open: false,
             config,
             config_path,
+            #[cfg(windows)]
             _tray_icon: tray_icon,
+            #[cfg(windows)]
             tray_receiver,
             hidden: false,
             poller,
-            feeders,
-            receivers,
-            records: Default::default(),
+            connection_events,
+            hotkey_sender,
+            hotkey_events,
+            adapter_errors: BTreeMap::new(),
+            captures: Vec::new(),
+            adapters: Vec::new(),
+            slots: BTreeMap::new(),
+        };
+
+        // Adapters opened before the subscription will not send an event.
+        for adapter in app.poller.adapters() {
+            app.attach_adapter(adapter);
         }
+
+        app
     }
 
     fn load_or_create_config(config_path: impl AsRef<Path>) -> Config {
@@ -156,50 +200,48 @@ impl App {
         }
     }
 
-    fn feeders_from_config(
-        config: &Config,
-        poller: &Poller<Usb>,
-    ) -> ([Feeder<Usb>; Port::COUNT], [feeder::Receiver; Port::COUNT]) {
-        let mut feeders: [Option<Feeder<Usb>>; Port::COUNT] = Default::default();
-        let mut receivers: [Option<feeder::Receiver>; Port::COUNT] = Default::default();
+    fn profile_from_config(config: &Config, port: Port) -> Profile {
+        let selected = &config.profile.selected[port.index()];
+        config
+            .profile
+            .list
+            .get(selected)
+            .cloned()
+            .unwrap_or_else(|| {
+                warn!(
+                    "Missing profile \'{}\' set for port {:?}, using default",
+                    selected, port
+                );
+                Profile::default()
+            })
+    }
 
-        for port in all::<Port>() {
-            let index = port.index();
-            let (feeder, receiver) = Self::feeder_from_config(config, poller, port);
-            feeders[index] = Some(feeder);
-            receivers[index] = Some(receiver);
+    /// The profiles that hotkeys switch `port` between.
+    fn profiles_from_config(config: &Config, port: Port) -> hotkey::Profiles {
+        hotkey::Profiles {
+            list: config.profile.list.clone().into_iter().collect(),
+            selected: config.profile.selected[port.index()].clone(),
         }
-
-        (feeders.map(Option::unwrap), receivers.map(Option::unwrap))
     }
 
-    fn feeder_from_config(
+    fn slot_from_config(
         config: &Config,
         poller: &Poller<Usb>,
-        port: Port,
-    ) -> (Feeder<Usb>, feeder::Receiver) {
+        controller: ControllerId,
+        serves_input: bool,
+        hotkey_sender: &channel::Sender<hotkey::Event>,
+    ) -> Slot {
+        let port = controller.port;
         let index = port.index();
-        let profile = {
-            let selected = &config.profile.selected[index];
-            config
-                .profile
-                .list
-                .get(selected)
-                .cloned()
-                .unwrap_or_else(|| {
-                    warn!(
-                        "Missing profile \'{}\' set for port {:?}, using default",
-                        selected, port
-                    );
-                    Profile::default()
-                })
-        };
-
-        let feeder = Feeder::new(profile, poller.add_listener(port));
+        let profile = Self::profile_from_config(config, port);
+        let feeder = Feeder::new(profile, poller.add_controller_listener(controller));
+        feeder.set_hotkeys(config.hotkeys.clone());
+        feeder.set_profiles(Self::profiles_from_config(config, port));
+        feeder.send_hotkeys(hotkey_sender.clone());
 
         let socket = {
             let server_config = &config.input_server[index];
-            if server_config.enabled {
+            if serves_input && server_config.enabled {
                 UdpSocket::bind(("127.0.0.1", server_config.port))
                     .and_then(|s| s.set_nonblocking(true).map(|()| s))
                     .map(Option::Some)
@@ -253,7 +295,65 @@ impl App {
 
         let (tx, rx) = recent::channel();
         feeder.send_on_feed(tx);
-        (feeder, rx)
+
+        Slot {
+            feeder,
+            receiver: rx,
+            record: None,
+        }
+    }
+
+    /// Adds a feeder for each port of `adapter`, unless it already has them.
+    fn attach_adapter(&mut self, adapter: AdapterId) {
+        if self.adapters.contains(&adapter) {
+            return;
+        }
+
+        let serves_input = self.adapters.is_empty();
+
+        for port in all::<Port>() {
+            let controller = ControllerId {
+                adapter: adapter.clone(),
+                port,
+            };
+            let slot = Self::slot_from_config(
+                &self.config,
+                &self.poller,
+                controller.clone(),
+                serves_input,
+                &self.hotkey_sender,
+            );
+            self.slots.insert(controller, slot);
+        }
+
+        self.adapters.push(adapter);
+    }
+
+    fn detach_adapter(&mut self, adapter: &AdapterId) {
+        let Some(index) = self.adapters.iter().position(|a| a == adapter) else {
+            return;
+        };
+
+        self.adapters.remove(index);
+        // Dropping the feeders closes their input servers before they are reopened.
+        self.slots
+            .retain(|controller, _| controller.adapter != *adapter);
+
+        if index == 0 {
+            if let Some(first) = self.adapters.first().cloned() {
+                for (controller, slot) in self.slots.iter_mut() {
+                    if controller.adapter == first {
+                        *slot = Self::slot_from_config(
+                            &self.config,
+                            &self.poller,
+                            controller.clone(),
+                            true,
+                            &self.hotkey_sender,
+                        );
+                    }
+                }
+            }
+        }
     }
 
     fn handle_messages(&mut self, frame: &mut eframe::Frame) {
@@ -261,6 +361,7 @@ impl App {
             frame.close();
         }
 
+        #[cfg(windows)]
         while let Ok(message) = self.tray_receiver.try_recv() {
             match message {
                 TrayMessage::Show => {
@@ -275,32 +376,60 @@ impl App {
             }
         }
 
-        for (i, (feeder, receiver)) in self
-            .feeders
-            .iter()
-            .zip(self.receivers.iter_mut())
-            .enumerate()
-        {
-            if !feeder.connected() {
-                self.records[i] = None;
+        while let Ok(event) = self.connection_events.try_recv() {
+            match event {
+                ConnectionEvent::OpenFailed { adapter, error } => {
+                    self.adapter_errors.insert(adapter, error);
+                }
+                ConnectionEvent::AdapterAttached(adapter) => {
+                    self.adapter_errors.remove(&adapter);
+                    self.attach_adapter(adapter);
+                }
+                ConnectionEvent::AdapterDetached(adapter) => {
+                    self.adapter_errors.remove(&adapter);
+                    self.detach_adapter(&adapter);
+                }
+            }
+        }
+
+        while let Ok(event) = self.hotkey_events.try_recv() {
+            self.apply_hotkey(event);
+        }
+
+        for slot in self.slots.values_mut() {
+            if !slot.feeder.connected() {
+                slot.record = None;
                 continue;
             }
 
-            match receiver.try_recv() {
+            match slot.receiver.try_recv() {
                 Ok(record) => {
-                    self.records[i] = Some(record);
+                    slot.record = Some(record);
                 }
                 Err(TryRecvError::Disconnected) => {
                     warn!("Feeder receiver disconnected while in use");
                     let (tx, rx) = recent::channel();
-                    feeder.send_on_feed(tx);
-                    *receiver = rx;
+                    slot.feeder.send_on_feed(tx);
+                    slot.receiver = rx;
                 }
                 _ => (),
             }
         }
     }
 
+    /// Shows the profile a hotkey switched to as selected, without saving it.
+    /// The feeder that fired the hotkey has already switched. Each port's
+    /// selection follows the first adapter, like the input servers.
+    fn apply_hotkey(&mut self, event: hotkey::Event) {
+        if self.adapters.first() != Some(&event.controller.adapter) {
+            return;
+        }
+
+        if let Some(profile) = event.profile {
+            self.config.profile.selected[event.controller.port.index()] = profile;
+        }
+    }
+
     pub fn save_config(&mut self) {
         Self::write_config(&self.config, &self.config_path);
         info!("Saved config");
@@ -310,12 +439,60 @@ impl App {
         }
     }
 
+    /// Captures every connected adapter's raw reports to a file next to the config.
+    pub fn start_capture(&mut self) {
+        let dir = self.config_path.parent().unwrap_or_else(|| Path::new(""));
+
+        for id in self.poller.adapters() {
+            let path = dir.join(format!("capture-{}.gccap", id));
+            let result = fs::File::create(&path)
+                .and_then(|file| self.poller.start_capture(&id, io::BufWriter::new(file)));
+
+            match result {
+                Ok(()) => {
+                    info!("Capturing adapter {} to {}", id, path.display());
+                    self.captures.push(id);
+                }
+                Err(e) => warn!("Failed to capture adapter {}: {}", id, e),
+            }
+        }
+    }
+
+    pub fn stop_capture(&mut self) {
+        for id in self.captures.drain(..) {
+            match self.poller.stop_capture(&id) {
+                Ok(()) => info!("Stopped capturing adapter {}", id),
+                Err(e) => warn!("Failed to finish capture of adapter {}: {}", id, e),
+            }
+        }
+    }
+
     pub fn reload_config(&mut self) {
         if let Some(config) = Self::load_config(&self.config_path) {
-            // TODO: Send config update to feeder instead of re-creating it.
-            let (feeders, receivers) = Self::feeders_from_config(&config, &self.poller);
-            self.feeders = feeders;
-            self.receivers = receivers;
+            for (controller, slot) in self.slots.iter_mut() {
+                let index = controller.port.index();
+                let serves_input = self.adapters.first() == Some(&controller.adapter);
+
+                if !serves_input || config.input_server[index] == self.config.input_server[index] {
+                    let feeder = &slot.feeder;
+                    feeder.update_config(Self::profile_from_config(&config, controller.port));
+                    feeder.set_hotkeys(config.hotkeys.clone());
+                    feeder.set_profiles(Self::profiles_from_config(&config, controller.port));
+                } else {
+                    // The input server is a feed callback, so it comes with a new feeder.
+                    *slot = Self::slot_from_config(
+                        &config,
+                        &self.poller,
+                        controller.clone(),
+                        true,
+                        &self.hotkey_sender,
+                    );
+                }
+            }
+
+            self.poller
+                .transport_context()
+                .set_quirks_override(config.adapter.quirks);
             self.config = config;
             info!("Reloaded config");
 
@@ -375,6 +552,16 @@ impl eframe::App for App {
                         ui.close_menu();
                     }
 
+                    if self.captures.is_empty() {
+                        if ui.button("Start Capture").clicked() {
+                            self.start_capture();
+                            ui.close_menu();
+                        }
+                    } else if ui.button("Stop Capture").clicked() {
+                        self.stop_capture();
+                        ui.close_menu();
+                    }
+
                     if ui.button("Exit").clicked() {
                         frame.close();
                     }
@@ -384,8 +571,17 @@ impl eframe::App for App {
                     if ui.toggle_value(&mut self.stats_open, "Stats").clicked() {
                         ui.close_menu();
                     }
+
+                    if ui
+                        .toggle_value(&mut self.trace_open, "Layer Trace")
+                        .clicked()
+                    {
+                        ui.close_menu();
+                    }
                 });
 
+                // Only the tray icon can show the window again.
+                #[cfg(windows)]
                 if ui.button("Hide").clicked() {
                     frame.set_visible(false);
                     self.hidden = true;
@@ -396,7 +592,20 @@ impl eframe::App for App {
         egui::Window::new("Stats")
             .open(&mut self.stats_open)
             .show(ctx, |ui| {
-                StatsPanel::new(&mut self.poller, &mut self.feeders).ui(ui);
+                StatsPanel::new(&mut self.poller, &self.slots, &self.adapter_errors).ui(ui);
+            });
+
+        // Feeders are recreated on reload and hotplug, so keep telling them.
+        for slot in self.slots.values() {
+            slot.feeder.set_tracing(self.trace_open);
+        }
+
+        egui::Window::new("Layer Trace")
+            .open(&mut self.trace_open)
+            .show(ctx, |ui| {
+                let mut panel = TracePanel::new(&self.slots, self.trace_state.take());
+                panel.ui(ui);
+                self.trace_state = Some(panel.into_state());
             });
 
         egui::TopBottomPanel::bottom("log_panel").show(ctx, |ui| {
@@ -404,12 +613,8 @@ impl eframe::App for App {
         });
 
         egui::SidePanel::left("calibration_panel").show(ctx, |ui| {
-            let mut panel = CalibrationPanel::new(
-                &mut self.feeders,
-                &self.records,
-                &self.config,
-                self.calibration_state.take(),
-            );
+            let mut panel =
+                CalibrationPanel::new(&self.slots, &self.config, self.calibration_state.take());
             panel.ui(ui);
             let (state, update) = panel.into_state();
             self.calibration_state = Some(state);
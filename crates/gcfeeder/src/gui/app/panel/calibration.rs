@@ -1,4 +1,4 @@
-use std::{convert::TryFrom, iter};
+use std::{collections::BTreeMap, iter};
 
 use egui::Color32;
 use gcinput::Input;
@@ -6,45 +6,34 @@ use gcinput::Input;
 use crate::{
     config::Config,
     gui::{
-        app::{widget, Usb},
-        util::enum_combo_ui,
+        app::{widget, Slot},
+        util::controller_combo_ui,
+        WARN_COLOR,
     },
 };
 use gcfeeder_core::{
-    adapter::Port,
-    calibration::{StickCalibration, SticksCalibration, TriggerCalibration, TriggersCalibration},
-    feeder::{CalibrationReceiver, Feeder, Record},
-    util::recent_channel as recent,
+    adapter::{ControllerId, ControllerKind, Port},
+    calibration::{
+        session::{Kind, Outcome, Step, StickId, TriggerId},
+        Session, StickCalibration, SticksCalibration, TriggerCalibration, TriggersCalibration,
+    },
+    feeder::RumbleSetting,
 };
 
-const NOTCH_NAMES: [&str; 8] = [
-    "top",
-    "top-right",
-    "right",
-    "bottom-right",
-    "bottom",
-    "bottom-left",
-    "left",
-    "top-left",
-];
-
 pub struct CalibrationPanel<'a> {
-    feeders: &'a mut [Feeder<Usb>; Port::COUNT],
-    records: &'a [Option<Record>; Port::COUNT],
+    slots: &'a BTreeMap<ControllerId, Slot>,
     config: &'a Config,
     state: State,
 }
 
 impl<'a> CalibrationPanel<'a> {
     pub fn new(
-        feeders: &'a mut [Feeder<Usb>; Port::COUNT],
-        records: &'a [Option<Record>; Port::COUNT],
+        slots: &'a BTreeMap<ControllerId, Slot>,
         config: &'a Config,
         state: Option<State>,
     ) -> Self {
         Self {
-            feeders,
-            records,
+            slots,
             config,
             state: state.unwrap_or_default(),
         }
@@ -77,13 +66,11 @@ impl<'a> CalibrationPanel<'a> {
     }
 
     #[must_use]
-    fn calibration_ui<R, P>(
+    fn session_ui(
         ui: &mut egui::Ui,
-        progress: &mut P,
-        connected: bool,
+        session: &Session,
         port: Port,
-        add_contents: impl FnOnce(&mut egui::Ui, &mut P) -> Option<R>,
-        apply_calibration: impl FnOnce(R),
+        config_update: &mut Option<ConfigUpdate>,
     ) -> Option<Action> {
         let mut next_action = None;
 
@@ -93,78 +80,113 @@ impl<'a> CalibrationPanel<'a> {
 
         ui.separator();
 
-        if !connected {
+        let Some(raw) = session.input() else {
             ui.label("Please reconnect the controller");
             return next_action;
-        }
+        };
+
+        let Some(step) = session.step() else {
+            ui.label(format!(
+                "Calibration finished. Apply to Port {:?} active profile?",
+                port
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Apply").clicked() {
+                    *config_update = session.outcome().map(|outcome| match outcome {
+                        Outcome::Sticks(calibration) => {
+                            ConfigUpdate::SticksCalibration { port, calibration }
+                        }
+                        Outcome::Triggers(calibration) => {
+                            ConfigUpdate::TriggersCalibration { port, calibration }
+                        }
+                    });
+                    next_action = Some(Action::DisplayInputs);
+                }
+
+                if ui.button("Discard").clicked() {
+                    next_action = Some(Action::DisplayInputs);
+                }
+            });
 
-        let r = match add_contents(ui, progress) {
-            Some(r) => r,
-            None => return next_action,
+            return next_action;
         };
 
-        ui.separator();
+        ui.label(match session.kind() {
+            Kind::Sticks => "Calibrating sticks...",
+            Kind::Triggers => "Calibrating triggers...",
+        });
 
-        ui.label(format!(
-            "Calibration finished. Apply to Port {:?} active profile?",
-            port
-        ));
-        ui.horizontal(|ui| {
-            if ui.button("Apply").clicked() {
-                apply_calibration(r);
-                next_action = Some(Action::DisplayInputs);
+        let (recorded, total) = session.progress();
+        ui.add(
+            egui::ProgressBar::new(recorded as f32 / total as f32)
+                .text(format!("{}/{}", recorded, total)),
+        );
+
+        match step {
+            Step::Center(stick) | Step::Notch(stick, _) => {
+                let points = session.stick_points(stick);
+                let widget = match stick {
+                    StickId::Main => Self::main_stick(&raw),
+                    StickId::C => Self::c_stick(&raw),
+                };
+                ui.add(widget.with_points(&points));
             }
-
-            if ui.button("Discard").clicked() {
-                next_action = Some(Action::DisplayInputs);
+            Step::Release(trigger) | Step::Press(trigger) => {
+                let markers = session.trigger_markers(trigger);
+                let widget = match trigger {
+                    TriggerId::Left => Self::left_trigger(&raw),
+                    TriggerId::Right => Self::right_trigger(&raw),
+                };
+                ui.add(widget.with_markers(&markers));
             }
-        });
+        }
 
-        next_action
-    }
+        ui.label(step.to_string());
 
-    fn should_confirm(was_a_pressed: &mut bool, input: &Input) -> bool {
-        if input.button_a && !*was_a_pressed {
-            *was_a_pressed = true;
-            true
-        } else if !input.button_a {
-            *was_a_pressed = false;
-            false
-        } else {
-            false
+        if let Some(rejection) = session.rejection() {
+            ui.colored_label(WARN_COLOR, format!("Try again, {}", rejection));
         }
+
+        next_action
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui) {
         let State {
-            port,
+            controller,
             action,
             config_update,
             view_calibration,
-            was_a_pressed,
         } = &mut self.state;
 
         ui.set_min_width(200.0);
         ui.heading("Calibration");
         ui.add_space(5.0);
 
-        let index = port.index();
-        let feeder = &self.feeders[index];
-
-        let connected = self.records[index]
-            .as_ref()
-            .map(|r| r.raw_input.is_some())
-            .unwrap_or(false);
-
         ui.add_enabled_ui(matches!(action, Action::DisplayInputs), |ui| {
-            enum_combo_ui(port, "Port", ui);
+            controller_combo_ui(controller, self.slots.keys(), "Controller", ui);
         });
 
         ui.separator();
 
+        let Some((port, slot)) = controller
+            .as_ref()
+            .and_then(|c| Some((c.port, self.slots.get(c)?)))
+        else {
+            ui.label("No adapters connected");
+            return;
+        };
+        let feeder = &slot.feeder;
+
+        let connected = slot
+            .record
+            .as_ref()
+            .map(|r| r.raw_input.is_some())
+            .unwrap_or(false);
+
         let scroll_contents = |ui: &mut egui::Ui| match action {
             Action::DisplayInputs => {
-                let (raw, mapped) = self.records[index]
+                let (raw, mapped) = slot
+                    .record
                     .as_ref()
                     .and_then(|r| r.raw_input.zip(r.layered_input))
                     .unwrap_or_default();
@@ -174,17 +196,35 @@ impl<'a> CalibrationPanel<'a> {
                     return;
                 }
 
+                if let Some(status) = slot.record.as_ref().map(|r| r.status) {
+                    let kind = match status.kind {
+                        ControllerKind::Wireless => "WaveBird",
+                        _ => "Wired",
+                    };
+                    ui.label(format!("{} controller", kind));
+
+                    let rumble_on = self
+                        .config
+                        .profile
+                        .selected(port)
+                        .map(|profile| profile.rumble == RumbleSetting::On)
+                        .unwrap_or(false);
+
+                    if rumble_on && !status.rumble_powered {
+                        ui.colored_label(
+                            WARN_COLOR,
+                            "Rumble is on but the adapter's rumble power cable is not plugged in",
+                        );
+                    }
+                }
+
                 ui.horizontal(|ui| {
                     if ui.button("Calibrate Sticks").clicked() {
-                        let (tx, rx) = recent::channel();
-                        feeder.start_calibration(tx);
-                        *action = Action::CalibrateSticks(Default::default(), rx);
+                        *action = Action::Calibrate(Session::start(feeder, Kind::Sticks));
                     }
 
                     if ui.button("Calibrate Triggers").clicked() {
-                        let (tx, rx) = recent::channel();
-                        feeder.start_calibration(tx);
-                        *action = Action::CalibrateTriggers(Default::default(), rx);
+                        *action = Action::Calibrate(Session::start(feeder, Kind::Triggers));
                     }
                 });
 
@@ -204,7 +244,7 @@ impl<'a> CalibrationPanel<'a> {
                     let (sticks, triggers) = self
                         .config
                         .profile
-                        .selected(*port)
+                        .selected(port)
                         .filter(|_| *view_calibration)
                         .map(|profile| {
                             (
@@ -245,154 +285,10 @@ impl<'a> CalibrationPanel<'a> {
                     ui.add(Self::right_trigger(&mapped).with_markers(&trigger_points));
                 });
             }
-            Action::CalibrateSticks(progress, rx) => {
-                let record = rx.try_recv().ok().flatten();
-                let raw = record.unwrap_or_default();
-                let confirm = Self::should_confirm(was_a_pressed, &raw);
-                let connected = record.is_some();
-
-                let next_action = Self::calibration_ui(
-                    ui,
-                    progress,
-                    connected,
-                    *port,
-                    |ui, progress| {
-                        ui.label("Calibrating sticks...");
-
-                        let mut is_main_stick = true;
-                        loop {
-                            let (stick, pstick, name) = if is_main_stick {
-                                (&raw.main_stick, &mut progress.main_stick, "main ")
-                            } else {
-                                (&raw.c_stick, &mut progress.c_stick, "C-")
-                            };
-
-                            let points = iter::once(pstick.center)
-                                .chain(pstick.notch_points)
-                                .flatten()
-                                .collect::<Vec<_>>();
-
-                            if is_main_stick {
-                                ui.add(Self::main_stick(&raw).with_points(&points));
-                            } else {
-                                ui.add(Self::c_stick(&raw).with_points(&points));
-                            }
-
-                            if pstick.center.is_none() {
-                                ui.label(format!("Center {}stick then press A", name));
-                                if confirm {
-                                    pstick.center = Some((*stick).into());
-                                }
-                                return None;
-                            }
-
-                            for (i, point) in pstick.notch_points.iter_mut().enumerate() {
-                                if point.is_none() {
-                                    ui.label(format!(
-                                        "Move {}stick to center then to {} then press A",
-                                        name, NOTCH_NAMES[i]
-                                    ));
-                                    if confirm {
-                                        *point = Some((*stick).into());
-                                    }
-                                    return None;
-                                }
-                            }
-
-                            if is_main_stick {
-                                is_main_stick = false;
-                            } else {
-                                break;
-                            }
-                        }
-
-                        Some(SticksCalibration::try_from(*progress).unwrap())
-                    },
-                    |calibration| {
-                        *config_update = Some(ConfigUpdate::SticksCalibration {
-                            port: *port,
-                            calibration,
-                        });
-                    },
-                );
-
-                if let Some(next_action) = next_action {
-                    *action = next_action;
-                }
-            }
-            Action::CalibrateTriggers(progress, rx) => {
-                let record = rx.try_recv().ok().flatten();
-                let raw = record.unwrap_or_default();
-                let confirm = Self::should_confirm(was_a_pressed, &raw);
-                let connected = record.is_some();
-
-                let next_action = Self::calibration_ui(
-                    ui,
-                    progress,
-                    connected,
-                    *port,
-                    |ui, progress| {
-                        ui.label("Calibrating triggers...");
-
-                        let mut is_left_trigger = true;
-                        loop {
-                            let (trigger, ptrigger, name) = if is_left_trigger {
-                                (&raw.left_trigger, &mut progress.left_trigger, "left")
-                            } else {
-                                (&raw.right_trigger, &mut progress.right_trigger, "right")
-                            };
-
-                            let markers = iter::once(ptrigger.min)
-                                .chain(iter::once(ptrigger.max))
-                                .flatten()
-                                .collect::<Vec<_>>();
-
-                            if is_left_trigger {
-                                ui.add(Self::left_trigger(&raw).with_markers(&markers));
-                            } else {
-                                ui.add(Self::right_trigger(&raw).with_markers(&markers));
-                            }
-
-                            if ptrigger.min.is_none() {
-                                ui.label(format!(
-                                    "Completely release {} trigger then press A",
-                                    name
-                                ));
-                                if confirm {
-                                    ptrigger.min = Some(*trigger);
-                                }
-                                return None;
-                            }
-
-                            if ptrigger.max.is_none() {
-                                ui.label(format!(
-                                    "Press {} trigger all the way in then press A",
-                                    name
-                                ));
-                                if confirm {
-                                    ptrigger.max = Some(*trigger);
-                                }
-                                return None;
-                            }
-
-                            if is_left_trigger {
-                                is_left_trigger = false;
-                            } else {
-                                break;
-                            }
-                        }
+            Action::Calibrate(session) => {
+                session.update();
 
-                        Some(TriggersCalibration::try_from(*progress).unwrap())
-                    },
-                    |calibration| {
-                        *config_update = Some(ConfigUpdate::TriggersCalibration {
-                            port: *port,
-                            calibration,
-                        });
-                    },
-                );
-
-                if let Some(next_action) = next_action {
+                if let Some(next_action) = Self::session_ui(ui, session, port, config_update) {
                     *action = next_action;
                 }
             }
@@ -402,30 +298,17 @@ impl<'a> CalibrationPanel<'a> {
     }
 }
 
+#[derive(Default)]
 pub struct State {
-    port: Port,
+    controller: Option<ControllerId>,
     action: Action,
     config_update: Option<ConfigUpdate>,
     view_calibration: bool,
-    was_a_pressed: bool,
-}
-
-impl Default for State {
-    fn default() -> Self {
-        Self {
-            port: Port::One,
-            action: Action::default(),
-            config_update: None,
-            view_calibration: false,
-            was_a_pressed: false,
-        }
-    }
 }
 
 enum Action {
     DisplayInputs,
-    CalibrateSticks(SticksProgress, CalibrationReceiver),
-    CalibrateTriggers(TriggersProgress, CalibrationReceiver),
+    Calibrate(Session),
 }
 
 impl Default for Action {
@@ -434,94 +317,6 @@ impl Default for Action {
     }
 }
 
-#[derive(Default, Copy, Clone)]
-struct StickProgress {
-    pub notch_points: [Option<[u8; 2]>; 8],
-    pub center: Option<[u8; 2]>,
-}
-
-impl TryFrom<StickProgress> for StickCalibration {
-    type Error = ();
-
-    fn try_from(value: StickProgress) -> Result<Self, Self::Error> {
-        let notch_points = value
-            .notch_points
-            .into_iter()
-            .collect::<Option<Vec<_>>>()
-            .map(|v| <[[u8; 2]; 8]>::try_from(v).unwrap());
-
-        notch_points
-            .and_then(|notch_points| {
-                value.center.map(|center| Self {
-                    notch_points,
-                    center,
-                })
-            })
-            .ok_or(())
-    }
-}
-
-#[derive(Default, Copy, Clone)]
-struct TriggerProgress {
-    pub min: Option<u8>,
-    pub max: Option<u8>,
-}
-
-impl TryFrom<TriggerProgress> for TriggerCalibration {
-    type Error = ();
-
-    fn try_from(value: TriggerProgress) -> Result<Self, Self::Error> {
-        value
-            .min
-            .and_then(|min| value.max.map(|max| Self { min, max }))
-            .ok_or(())
-    }
-}
-
-#[derive(Default, Copy, Clone)]
-struct SticksProgress {
-    main_stick: StickProgress,
-    c_stick: StickProgress,
-}
-
-impl TryFrom<SticksProgress> for SticksCalibration {
-    type Error = ();
-
-    fn try_from(value: SticksProgress) -> Result<Self, Self::Error> {
-        let main_stick = StickCalibration::try_from(value.main_stick);
-        let c_stick = StickCalibration::try_from(value.c_stick);
-
-        main_stick.and_then(|main_stick| {
-            c_stick.map(|c_stick| Self {
-                main_stick,
-                c_stick,
-            })
-        })
-    }
-}
-
-#[derive(Default, Copy, Clone)]
-struct TriggersProgress {
-    left_trigger: TriggerProgress,
-    right_trigger: TriggerProgress,
-}
-
-impl TryFrom<TriggersProgress> for TriggersCalibration {
-    type Error = ();
-
-    fn try_from(value: TriggersProgress) -> Result<Self, Self::Error> {
-        let left_trigger = TriggerCalibration::try_from(value.left_trigger);
-        let right_trigger = TriggerCalibration::try_from(value.right_trigger);
-
-        left_trigger.and_then(|left_trigger| {
-            right_trigger.map(|right_trigger| Self {
-                left_trigger,
-                right_trigger,
-            })
-        })
-    }
-}
-
 #[derive(Debug)]
 pub enum ConfigUpdate {
     SticksCalibration {
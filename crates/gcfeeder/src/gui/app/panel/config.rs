@@ -2,7 +2,10 @@ use enum_iterator::all;
 
 use crate::{
     config::Config,
-    gui::{util::no_close_popup_below_widget, ERROR_COLOR},
+    gui::{
+        util::{enum_option_combo_ui, no_close_popup_below_widget},
+        ERROR_COLOR,
+    },
 };
 use gcfeeder_core::adapter::Port;
 
@@ -175,6 +178,15 @@ impl<'a> ConfigEditor<'a> {
                         ui.end_row();
                     }
                 });
+
+            ui.separator();
+
+            ui.label("Adapter");
+            let quirks = self.config.adapter.quirks;
+            enum_option_combo_ui(&mut self.config.adapter.quirks, "Quirks Override", ui);
+            if self.config.adapter.quirks != quirks {
+                *dirty = true;
+            }
         });
     }
 }
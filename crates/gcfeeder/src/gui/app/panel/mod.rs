@@ -3,8 +3,9 @@ pub mod config;
 pub mod log;
 pub mod profile;
 pub mod stats;
+pub mod trace;
 
 pub use self::{
     calibration::CalibrationPanel, config::ConfigEditor, log::LogPanel, profile::ProfilePanel,
-    stats::StatsPanel,
+    stats::StatsPanel, trace::TracePanel,
 };
@@ -1,10 +1,19 @@
+use egui::Color32;
+
 use crate::{
     config::{Config, Profile},
-    gui::util::{enum_combo_ui, enum_option_combo_ui},
+    gui::{
+        app::widget,
+        util::{enum_combo_ui, enum_option_combo_ui},
+    },
+};
+use gcfeeder_core::{
+    calibration::{StickCalibration, TriggerCalibration, NOTCHES},
+    mapping::pipeline::{self, LayerConfig, LayerSpec, ResponseCurveConfig},
 };
-use gcfeeder_core::calibration::{StickCalibration, TriggerCalibration, NOTCHES};
 
 const U8_TEXT_WIDTH: f32 = 30.0;
+const CURVE_PREVIEW_SAMPLES: usize = 32;
 
 pub struct ProfilePanel<'a> {
     config: &'a mut Config,
@@ -19,15 +28,23 @@ impl<'a> ProfilePanel<'a> {
         state: Option<State>,
     ) -> ProfilePanel<'a> {
         let state = state.map(|s| s.reset()).unwrap_or_else(|| {
-            let profile = *config
+            let profile = config
                 .profile
                 .list
                 .get(profile_name)
+                .cloned()
                 .expect("Active profile exists");
 
+            let layer_params = profile
+                .layers
+                .iter()
+                .map(|spec| toml::to_string(&spec.params).unwrap_or_default())
+                .collect();
+
             State {
                 message: None,
                 profile,
+                layer_params,
             }
         });
 
@@ -53,7 +70,7 @@ impl<'a> ProfilePanel<'a> {
                 self.config
                     .profile
                     .list
-                    .insert(self.profile_name.to_string(), self.state.profile);
+                    .insert(self.profile_name.to_string(), self.state.profile.clone());
                 self.state.message = Some(Message::SaveReload);
             }
 
@@ -69,8 +86,33 @@ impl<'a> ProfilePanel<'a> {
         egui::ScrollArea::vertical().show(ui, |ui| {
             let profile = &mut self.state.profile;
 
-            enum_combo_ui(&mut profile.driver, "Driver", ui);
+            ui.group(|ui| {
+                ui.label("Drivers");
+
+                let mut removed = None;
+                for (i, driver) in profile.drivers.iter_mut().enumerate() {
+                    ui.push_id(i, |ui| {
+                        ui.horizontal(|ui| {
+                            enum_combo_ui(driver, "", ui);
+
+                            if ui.button("Remove").clicked() {
+                                removed = Some(i);
+                            }
+                        });
+                    });
+                }
+
+                if let Some(i) = removed {
+                    profile.drivers.remove(i);
+                }
+
+                if ui.button("Add Driver").clicked() {
+                    profile.drivers.push(Default::default());
+                }
+            });
+
             enum_combo_ui(&mut profile.rumble, "Rumble", ui);
+            enum_combo_ui(&mut profile.rumble_source, "Rumble Source", ui);
 
             ui.horizontal(|ui| {
                 ui.scope(|ui| {
@@ -96,6 +138,29 @@ impl<'a> ProfilePanel<'a> {
                 enum_combo_ui(&mut profile.vigem_config.trigger_mode, "Trigger Mode", ui);
             });
 
+            ui.group(|ui| {
+                ui.label("uinput");
+
+                enum_combo_ui(&mut profile.uinput_config.trigger_mode, "Trigger Mode", ui);
+            });
+
+            ui.group(|ui| {
+                ui.label("DSU");
+
+                ui.horizontal(|ui| {
+                    let mut buf = profile.dsu_config.address.to_string();
+                    if ui.text_edit_singleline(&mut buf).changed() {
+                        if let Ok(address) = buf.parse() {
+                            profile.dsu_config.address = address;
+                        }
+                    }
+
+                    ui.label("Address");
+                });
+
+                enum_combo_ui(&mut profile.dsu_config.trigger_mode, "Trigger Mode", ui);
+            });
+
             ui.group(|ui| {
                 ui.label("Calibration");
 
@@ -248,13 +313,110 @@ impl<'a> ProfilePanel<'a> {
             });
 
             enum_option_combo_ui(&mut profile.ess.inversion_mapping, "Ess Inversion", ui);
+
+            ui.group(|ui| {
+                ui.label("Layers");
+
+                let layer_params = &mut self.state.layer_params;
+                let mut action = None;
+
+                for (i, (spec, params)) in profile
+                    .layers
+                    .iter_mut()
+                    .zip(layer_params.iter_mut())
+                    .enumerate()
+                {
+                    ui.push_id(i, |ui| {
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                            ui.label(&spec.kind);
+
+                            if ui.button("Up").clicked() {
+                                action = Some(LayerAction::Swap(i, i.saturating_sub(1)));
+                            }
+
+                            if ui.button("Down").clicked() {
+                                action = Some(LayerAction::Swap(i, i + 1));
+                            }
+
+                            if ui.button("Remove").clicked() {
+                                action = Some(LayerAction::Remove(i));
+                            }
+                        });
+
+                        if ui.code_editor(params).changed() {
+                            if let Ok(table) = toml::from_str(params) {
+                                spec.params = table;
+                            }
+                        }
+
+                        if toml::from_str::<toml::value::Table>(params).is_err() {
+                            ui.label("Invalid parameters");
+                        }
+
+                        if spec.kind == ResponseCurveConfig::KIND {
+                            let curves = toml::Value::Table(spec.params.clone())
+                                .try_into::<ResponseCurveConfig>();
+
+                            if let Ok(curves) = curves {
+                                ui.horizontal(|ui| {
+                                    for (curve, color) in [
+                                        (&curves.main_stick, Color32::WHITE),
+                                        (&curves.c_stick, Color32::YELLOW),
+                                    ] {
+                                        let points = curve.preview(CURVE_PREVIEW_SAMPLES);
+                                        ui.add(widget::Curve::new(&points, color));
+                                    }
+                                });
+                            }
+                        }
+                    });
+                }
+
+                ui.separator();
+
+                egui::ComboBox::from_label("Add Layer")
+                    .selected_text("Select a layer...")
+                    .show_ui(ui, |ui| {
+                        for kind in pipeline::kinds() {
+                            if ui.selectable_label(false, kind).clicked() {
+                                action = Some(LayerAction::Add(kind));
+                            }
+                        }
+                    });
+
+                match action {
+                    Some(LayerAction::Swap(a, b)) if b < profile.layers.len() => {
+                        profile.layers.swap(a, b);
+                        layer_params.swap(a, b);
+                    }
+                    Some(LayerAction::Remove(i)) => {
+                        profile.layers.remove(i);
+                        layer_params.remove(i);
+                    }
+                    Some(LayerAction::Add(kind)) => {
+                        profile.layers.push(LayerSpec::new(kind));
+                        layer_params.push(String::new());
+                    }
+                    _ => {}
+                }
+            });
         });
     }
 }
 
+enum LayerAction {
+    Swap(usize, usize),
+    Remove(usize),
+    Add(&'static str),
+}
+
 pub struct State {
     message: Option<Message>,
     profile: Profile,
+    /// The TOML being edited for each layer's parameters.
+    layer_params: Vec<String>,
 }
 
 impl State {
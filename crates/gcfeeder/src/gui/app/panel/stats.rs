@@ -1,47 +1,128 @@
-use crate::gui::app::Usb;
-use enum_iterator::all;
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::gui::{
+    app::{Slot, Usb},
+    util::controller_label,
+    ERROR_COLOR, WARN_COLOR,
+};
 use gcfeeder_core::{
-    adapter::{poller::Poller, Port},
-    feeder::Feeder,
+    adapter::{self, poller::Poller, AdapterId, ControllerId},
+    util::metrics::Summary,
 };
 
 pub struct StatsPanel<'a> {
     poller: &'a mut Poller<Usb>,
-    feeders: &'a mut [Feeder<Usb>; Port::COUNT],
+    slots: &'a BTreeMap<ControllerId, Slot>,
+    adapter_errors: &'a BTreeMap<AdapterId, adapter::Error>,
 }
 
 impl<'a> StatsPanel<'a> {
-    pub fn new(poller: &'a mut Poller<Usb>, feeders: &'a mut [Feeder<Usb>; Port::COUNT]) -> Self {
-        Self { poller, feeders }
+    pub fn new(
+        poller: &'a mut Poller<Usb>,
+        slots: &'a BTreeMap<ControllerId, Slot>,
+        adapter_errors: &'a BTreeMap<AdapterId, adapter::Error>,
+    ) -> Self {
+        Self {
+            poller,
+            slots,
+            adapter_errors,
+        }
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui) {
         ui.set_min_width(200.0);
 
-        let poll_avg = self
-            .poller
-            .average_poll_time()
-            .filter(|_| self.poller.connected())
-            .map(|d| format!("{:.2}", d.as_secs_f64() * 1000.0))
-            .unwrap_or_else(|| "-".to_owned());
+        if ui.button("Reset").clicked() {
+            self.poller.clear_poll_times();
 
-        ui.heading("Average poll time");
-        ui.label(format!("{}ms", poll_avg));
+            for slot in self.slots.values() {
+                slot.feeder.clear_feed_times();
+            }
+        }
+
+        ui.heading("Poll time");
+
+        let adapters = self.poller.adapters();
+        if adapters.is_empty() {
+            ui.label("No adapters connected");
+        }
+
+        egui::Grid::new("poll_times").striped(true).show(ui, |ui| {
+            if !adapters.is_empty() {
+                Self::header_ui(ui, "Adapter");
+            }
+
+            for adapter in adapters {
+                let report_rate = self
+                    .poller
+                    .report_interval(&adapter)
+                    .map(|d| format!("{:.0}", 1.0 / d.as_secs_f64()))
+                    .unwrap_or_else(|| "-".to_owned());
+
+                Self::summary_ui(
+                    ui,
+                    format!("{} ({}Hz)", adapter, report_rate),
+                    self.poller.adapter_poll_times(&adapter),
+                );
+            }
+        });
+
+        for (adapter, error) in self.adapter_errors.iter() {
+            ui.colored_label(
+                ERROR_COLOR,
+                format!("Adapter {} failed to open: {}", adapter, error),
+            );
+        }
 
         ui.add_space(5.0);
 
-        ui.heading("Average feed time");
+        ui.heading("Feed time");
+
+        egui::Grid::new("feed_times").striped(true).show(ui, |ui| {
+            Self::header_ui(ui, "Port");
+
+            for (controller, slot) in self.slots.iter() {
+                let feeder = &slot.feeder;
+                let summary = feeder.feed_times().filter(|_| feeder.connected());
+
+                Self::summary_ui(ui, controller_label(controller), summary);
+            }
+        });
+    }
+
+    fn header_ui(ui: &mut egui::Ui, source: &str) {
+        for heading in [
+            source, "Min", "p50", "p99", "p99.9", "Max", "Jitter", "Overruns",
+        ] {
+            ui.strong(heading);
+        }
+        ui.end_row();
+    }
 
-        for port in all::<Port>() {
-            let feeder = &self.feeders[port.index()];
+    fn summary_ui(ui: &mut egui::Ui, source: String, summary: Option<Summary>) {
+        let millis = |d: Duration| format!("{:.2}ms", d.as_secs_f64() * 1000.0);
 
-            let feed_avg = feeder
-                .average_feed_time()
-                .filter(|_| feeder.connected())
-                .map(|d| format!("{:.2}", d.as_secs_f64() * 1000.0))
-                .unwrap_or_else(|| "-".to_owned());
+        ui.label(source);
 
-            ui.label(format!("Port {:?}: {}ms", port, feed_avg));
+        match summary {
+            Some(s) => {
+                for d in [s.min, s.p50, s.p99, s.p999, s.max, s.jitter] {
+                    ui.monospace(millis(d));
+                }
+
+                if s.overruns > 0 {
+                    ui.colored_label(WARN_COLOR, s.overruns.to_string());
+                } else {
+                    ui.label("0");
+                }
+            }
+            None => {
+                for _ in 0..7 {
+                    ui.label("-");
+                }
+            }
         }
+
+        ui.end_row();
     }
 }
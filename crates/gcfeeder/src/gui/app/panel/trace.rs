@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use egui::{Color32, RichText};
+use gcinput::Input;
+
+use crate::gui::{app::Slot, util::controller_combo_ui};
+use gcfeeder_core::{adapter::ControllerId, feeder::Record};
+
+pub struct TracePanel<'a> {
+    slots: &'a BTreeMap<ControllerId, Slot>,
+    state: State,
+}
+
+impl<'a> TracePanel<'a> {
+    pub fn new(slots: &'a BTreeMap<ControllerId, Slot>, state: Option<State>) -> Self {
+        Self {
+            slots,
+            state: state.unwrap_or_default(),
+        }
+    }
+
+    #[must_use]
+    pub fn into_state(self) -> State {
+        self.state
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let State { controller, paused } = &mut self.state;
+
+        ui.set_min_width(300.0);
+
+        ui.horizontal(|ui| {
+            let prev_controller = controller.clone();
+            controller_combo_ui(controller, self.slots.keys(), "Controller", ui);
+
+            if *controller != prev_controller {
+                *paused = None;
+            }
+
+            let mut is_paused = paused.is_some();
+            if ui.toggle_value(&mut is_paused, "Pause").clicked() {
+                *paused = if is_paused {
+                    Self::latest(self.slots, controller.as_ref()).cloned()
+                } else {
+                    None
+                };
+            }
+        });
+
+        ui.separator();
+
+        let record = paused
+            .as_ref()
+            .or_else(|| Self::latest(self.slots, controller.as_ref()));
+        let Some(trace) = record.and_then(|r| r.trace.as_ref()) else {
+            ui.label("Waiting for input");
+            return;
+        };
+
+        egui::Grid::new("trace_grid").striped(true).show(ui, |ui| {
+            for heading in ["Layer", "Main", "C", "L", "R", "Buttons"] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+
+            let mut previous: Option<[String; 5]> = None;
+
+            for step in trace {
+                ui.label(step.layer);
+
+                let Some(input) = step.output.as_ref() else {
+                    ui.label("Disconnected");
+                    ui.end_row();
+                    previous = None;
+                    continue;
+                };
+
+                let cells = cells(input);
+                for (i, cell) in cells.iter().enumerate() {
+                    let changed = previous.as_ref().is_none_or(|p| p[i] != *cell);
+                    let text = RichText::new(cell).monospace();
+
+                    if changed {
+                        ui.label(text.color(Color32::YELLOW));
+                    } else {
+                        ui.label(text);
+                    }
+                }
+                ui.end_row();
+
+                previous = Some(cells);
+            }
+        });
+    }
+
+    fn latest<'b>(
+        slots: &'b BTreeMap<ControllerId, Slot>,
+        controller: Option<&ControllerId>,
+    ) -> Option<&'b Record> {
+        slots.get(controller?)?.record.as_ref()
+    }
+}
+
+/// The input's sticks, triggers and held buttons as text.
+fn cells(input: &Input) -> [String; 5] {
+    let buttons = [
+        ("A", input.button_a),
+        ("B", input.button_b),
+        ("X", input.button_x),
+        ("Y", input.button_y),
+        ("Z", input.button_z),
+        ("Start", input.button_start),
+        ("L", input.button_l),
+        ("R", input.button_r),
+        ("Up", input.button_up),
+        ("Down", input.button_down),
+        ("Left", input.button_left),
+        ("Right", input.button_right),
+    ]
+    .into_iter()
+    .filter_map(|(name, pressed)| pressed.then_some(name))
+    .collect::<Vec<_>>()
+    .join(" ");
+
+    [
+        format!("{:3} {:3}", input.main_stick.x, input.main_stick.y),
+        format!("{:3} {:3}", input.c_stick.x, input.c_stick.y),
+        format!("{:3}", input.left_trigger),
+        format!("{:3}", input.right_trigger),
+        buttons,
+    ]
+}
+
+#[derive(Default)]
+pub struct State {
+    controller: Option<ControllerId>,
+    /// The record being inspected instead of the latest one.
+    paused: Option<Record>,
+}
@@ -0,0 +1,63 @@
+use eframe::epaint;
+use egui::{Color32, Pos2, Rgba, Rounding, Sense, Stroke, Vec2};
+
+/// Plots `[input, output]` points from 0 to 1 against the identity line.
+pub struct Curve<'a> {
+    points: &'a [[f64; 2]],
+    color: Color32,
+}
+
+impl<'a> Curve<'a> {
+    const SIZE: f32 = 45.0;
+
+    pub fn new(points: &'a [[f64; 2]], color: Color32) -> Self {
+        Self { points, color }
+    }
+}
+
+impl egui::Widget for Curve<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let (rect, response) = ui.allocate_exact_size(
+            Vec2::new(Self::SIZE, Self::SIZE),
+            Sense::focusable_noninteractive(),
+        );
+
+        if ui.is_rect_visible(response.rect) {
+            let painter = ui.painter();
+            let color = Rgba::from(self.color);
+            let background_color =
+                Rgba::from_rgba_unmultiplied(color.r(), color.g(), color.b(), color.a() * 0.03);
+            let border_color =
+                Rgba::from_rgba_unmultiplied(color.r(), color.g(), color.b(), color.a() * 0.35);
+
+            // Add background rect.
+            painter.add(epaint::RectShape {
+                rect,
+                rounding: Rounding::none(),
+                fill: background_color.into(),
+                stroke: Stroke::new(1.0, border_color),
+            });
+
+            // Add identity line.
+            painter.line_segment(
+                [rect.left_bottom(), rect.right_top()],
+                Stroke::new(1.0, border_color),
+            );
+
+            let to_pos = |[x, y]: [f64; 2]| {
+                Pos2::new(
+                    rect.left() + x as f32 * Self::SIZE,
+                    rect.bottom() - y as f32 * Self::SIZE,
+                )
+            };
+
+            // Add curve.
+            painter.add(epaint::PathShape::line(
+                self.points.iter().copied().map(to_pos).collect(),
+                Stroke::new(1.5, self.color),
+            ));
+        }
+
+        response
+    }
+}
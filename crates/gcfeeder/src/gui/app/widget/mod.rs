@@ -1,6 +1,7 @@
 mod shape;
 
+pub mod curve;
 pub mod stick;
 pub mod trigger;
 
-pub use self::{stick::Stick, trigger::Trigger};
+pub use self::{curve::Curve, stick::Stick, trigger::Trigger};
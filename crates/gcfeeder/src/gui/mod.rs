@@ -1,9 +1,12 @@
 use std::env;
 
-use app::{App, TrayMessage};
+use app::App;
+#[cfg(windows)]
+use app::TrayMessage;
 use crossbeam::channel;
 use egui::Color32;
 use image::EncodableLayout;
+#[cfg(windows)]
 use trayicon::{MenuBuilder, TrayIconBuilder};
 
 mod app;
@@ -26,6 +29,7 @@ pub fn run() {
         .expect("Failed to set logger");
 
     const ICON_FILE: &[u8] = include_bytes!("../../resource/icon.png");
+    #[cfg(windows)]
     const ICON_ICO: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/icon.ico"));
 
     let icon = image::load_from_memory(ICON_FILE).unwrap();
@@ -43,8 +47,9 @@ pub fn run() {
         ..Default::default()
     };
 
+    #[cfg(windows)]
     let (tray_tx, tray_rx) = channel::unbounded();
-
+    #[cfg(windows)]
     let tray_icon = TrayIconBuilder::new()
         .sender_crossbeam(tray_tx)
         .icon_from_buffer(ICON_ICO)
@@ -67,6 +72,14 @@ pub fn run() {
     eframe::run_native(
         format!("gcfeeder | {}", version_string).as_str(),
         options,
-        Box::new(move |_cc| Box::new(App::new(tray_icon, tray_rx, log_rx))),
+        Box::new(move |_cc| {
+            Box::new(App::new(
+                #[cfg(windows)]
+                tray_icon,
+                #[cfg(windows)]
+                tray_rx,
+                log_rx,
+            ))
+        }),
     );
 }
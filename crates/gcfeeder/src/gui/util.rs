@@ -2,6 +2,7 @@ use std::fmt;
 
 use egui::{Align, Layout, Order, WidgetText};
 use enum_iterator::{all, Sequence};
+use gcfeeder_core::adapter::ControllerId;
 
 pub fn enum_combo_ui<T>(e: &mut T, label: impl Into<WidgetText>, ui: &mut egui::Ui)
 where
@@ -37,6 +38,41 @@ where
         });
 }
 
+pub fn controller_label(controller: &ControllerId) -> String {
+    format!("Port {:?} ({})", controller.port, controller.adapter)
+}
+
+/// Selects one of `controllers`, or the first of them if the selected one is
+/// gone. Leaves `selected` empty if there are none.
+pub fn controller_combo_ui<'a>(
+    selected: &mut Option<ControllerId>,
+    controllers: impl IntoIterator<Item = &'a ControllerId>,
+    label: impl Into<WidgetText>,
+    ui: &mut egui::Ui,
+) {
+    let controllers = controllers.into_iter().collect::<Vec<_>>();
+
+    if selected.as_ref().is_none_or(|s| !controllers.contains(&s)) {
+        *selected = controllers.first().map(|&c| c.clone());
+    }
+
+    egui::ComboBox::from_label(label)
+        .selected_text(
+            selected
+                .as_ref()
+                .map_or_else(|| "No adapters connected".to_owned(), controller_label),
+        )
+        .show_ui(ui, |ui| {
+            for controller in controllers {
+                ui.selectable_value(
+                    selected,
+                    Some(controller.clone()),
+                    controller_label(controller),
+                );
+            }
+        });
+}
+
 /// Implementation based on `egui::popup_below_widget`.
 pub fn no_close_popup_below_widget<R>(
     ui: &egui::Ui,
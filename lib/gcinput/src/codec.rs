@@ -0,0 +1,319 @@
+//! The WUP-028 adapter's USB report format.
+//!
+//! An input report is the report id followed by nine bytes for each of the four
+//! ports: a status byte, two button bytes, the main stick, the C-stick and the
+//! left and right triggers. A rumble report is its report id followed by one
+//! byte per port.
+//!
+//! Encoding is canonical, so `decode(&encode(&reports))` gives back `reports`
+//! whenever each report's input is present exactly when a controller is, and
+//! `encode(&decode(&payload)?)` is the canonical form of any payload.
+
+use std::{error::Error, fmt};
+
+use crate::{Input, Rumble, Stick};
+
+pub const PORT_COUNT: usize = 4;
+
+/// Bytes taken up by each port in an input report.
+pub const PORT_LEN: usize = 9;
+
+pub const INPUT_REPORT_ID: u8 = 0x21;
+pub const RUMBLE_REPORT_ID: u8 = 0x11;
+/// Starts the adapter's input reports.
+pub const START_COMMAND: u8 = 0x13;
+
+pub const PAYLOAD_LEN: usize = 1 + PORT_COUNT * PORT_LEN;
+pub const RUMBLE_PAYLOAD_LEN: usize = 1 + PORT_COUNT;
+
+pub type Payload = [u8; PAYLOAD_LEN];
+pub type RumblePayload = [u8; RUMBLE_PAYLOAD_LEN];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The payload does not start with the expected report id.
+    ReportId(u8),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReportId(id) => write!(f, "unexpected report id {:#04x}", id),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ControllerKind {
+    #[default]
+    None,
+    Wired,
+    Wireless,
+}
+
+impl ControllerKind {
+    /// Decodes the type nibble of a port's status byte.
+    pub const fn from_nibble(nibble: u8) -> Self {
+        match nibble {
+            0 => Self::None,
+            2 => Self::Wireless,
+            // Other values have not been observed, treat them as a plugged in controller.
+            _ => Self::Wired,
+        }
+    }
+
+    pub const fn to_nibble(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Wired => 1,
+            Self::Wireless => 2,
+        }
+    }
+}
+
+/// What the adapter reports about a port besides the controller's inputs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct ControllerStatus {
+    pub kind: ControllerKind,
+    /// Whether the adapter's second USB cable is plugged in, without which
+    /// controllers cannot rumble.
+    pub rumble_powered: bool,
+}
+
+impl ControllerStatus {
+    const RUMBLE_POWER_BIT: u8 = 1 << 2;
+
+    pub const fn from_byte(status: u8) -> Self {
+        Self {
+            kind: ControllerKind::from_nibble(status >> 4),
+            rumble_powered: status & Self::RUMBLE_POWER_BIT != 0,
+        }
+    }
+
+    pub const fn to_byte(self) -> u8 {
+        let power = if self.rumble_powered {
+            Self::RUMBLE_POWER_BIT
+        } else {
+            0
+        };
+
+        (self.kind.to_nibble() << 4) | power
+    }
+}
+
+/// A single port's share of an input report.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct PortReport {
+    pub status: ControllerStatus,
+    pub input: Option<Input>,
+}
+
+/// Decodes an input report, checking its report id.
+pub fn decode(payload: &Payload) -> Result<[PortReport; PORT_COUNT], DecodeError> {
+    if payload[0] == INPUT_REPORT_ID {
+        Ok(decode_ports(payload))
+    } else {
+        Err(DecodeError::ReportId(payload[0]))
+    }
+}
+
+/// Decodes an input report without looking at its report id.
+pub fn decode_ports(payload: &Payload) -> [PortReport; PORT_COUNT] {
+    let mut reports = [PortReport::default(); PORT_COUNT];
+
+    for (report, bytes) in reports.iter_mut().zip(payload[1..].chunks_exact(PORT_LEN)) {
+        report.status = ControllerStatus::from_byte(bytes[0]);
+
+        if report.status.kind != ControllerKind::None {
+            report.input = Some(decode_input(bytes[1..].try_into().unwrap()));
+        }
+    }
+
+    reports
+}
+
+/// Encodes an input report.
+///
+/// Ports without a controller are written as all zeros after their status byte,
+/// and connected ports without an input are written with a neutral one.
+pub fn encode(reports: &[PortReport; PORT_COUNT]) -> Payload {
+    let mut payload = [0_u8; PAYLOAD_LEN];
+    payload[0] = INPUT_REPORT_ID;
+
+    for (report, bytes) in reports.iter().zip(payload[1..].chunks_exact_mut(PORT_LEN)) {
+        bytes[0] = report.status.to_byte();
+
+        if report.status.kind != ControllerKind::None {
+            let input = report.input.unwrap_or_default();
+            bytes[1..].copy_from_slice(&encode_input(&input));
+        }
+    }
+
+    payload
+}
+
+pub fn decode_input(bytes: &[u8; PORT_LEN - 1]) -> Input {
+    let [b1, b2, main_x, main_y, c_x, c_y, left_trigger, right_trigger] = *bytes;
+    let bit = |byte: u8, n: u8| byte & (1 << n) != 0;
+
+    Input {
+        button_a: bit(b1, 0),
+        button_b: bit(b1, 1),
+        button_x: bit(b1, 2),
+        button_y: bit(b1, 3),
+
+        button_left: bit(b1, 4),
+        button_right: bit(b1, 5),
+        button_down: bit(b1, 6),
+        button_up: bit(b1, 7),
+
+        button_start: bit(b2, 0),
+        button_z: bit(b2, 1),
+        button_r: bit(b2, 2),
+        button_l: bit(b2, 3),
+
+        main_stick: Stick::new(main_x, main_y),
+        c_stick: Stick::new(c_x, c_y),
+        left_trigger,
+        right_trigger,
+    }
+}
+
+pub fn encode_input(input: &Input) -> [u8; PORT_LEN - 1] {
+    let bits = |bits: [bool; 8]| {
+        bits.iter()
+            .enumerate()
+            .fold(0_u8, |byte, (n, &set)| byte | (u8::from(set) << n))
+    };
+
+    let b1 = bits([
+        input.button_a,
+        input.button_b,
+        input.button_x,
+        input.button_y,
+        input.button_left,
+        input.button_right,
+        input.button_down,
+        input.button_up,
+    ]);
+    let b2 = bits([
+        input.button_start,
+        input.button_z,
+        input.button_r,
+        input.button_l,
+        false,
+        false,
+        false,
+        false,
+    ]);
+
+    [
+        b1,
+        b2,
+        input.main_stick.x,
+        input.main_stick.y,
+        input.c_stick.x,
+        input.c_stick.y,
+        input.left_trigger,
+        input.right_trigger,
+    ]
+}
+
+/// Decodes a rumble report, treating any non-zero byte as rumbling.
+pub fn decode_rumble(payload: &RumblePayload) -> Result<[Rumble; PORT_COUNT], DecodeError> {
+    if payload[0] != RUMBLE_REPORT_ID {
+        return Err(DecodeError::ReportId(payload[0]));
+    }
+
+    let mut states = [Rumble::Off; PORT_COUNT];
+    for (state, &byte) in states.iter_mut().zip(&payload[1..]) {
+        *state = Rumble::from(byte != 0);
+    }
+
+    Ok(states)
+}
+
+pub fn encode_rumble(states: [Rumble; PORT_COUNT]) -> RumblePayload {
+    let mut payload = [RUMBLE_REPORT_ID; RUMBLE_PAYLOAD_LEN];
+    for (byte, state) in payload[1..].iter_mut().zip(states) {
+        *byte = state.into();
+    }
+
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small xorshift generator so the round-trip checks cover many values
+    /// without pulling in a property testing crate.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u8 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0.to_le_bytes()[0]
+        }
+
+        fn payload(&mut self) -> Payload {
+            let mut payload = [0_u8; PAYLOAD_LEN];
+            payload.iter_mut().for_each(|b| *b = self.next());
+            payload[0] = INPUT_REPORT_ID;
+            payload
+        }
+    }
+
+    #[test]
+    fn decode_reads_buttons_and_status() {
+        let mut payload = [0_u8; PAYLOAD_LEN];
+        payload[0] = INPUT_REPORT_ID;
+        payload[1..10].copy_from_slice(&[0x24, 0x81, 0x08, 0x10, 0xF0, 0x80, 0x80, 0x00, 0xFF]);
+
+        let reports = decode(&payload).unwrap();
+        let input = reports[0].input.unwrap();
+        assert_eq!(reports[0].status.kind, ControllerKind::Wireless);
+        assert!(reports[0].status.rumble_powered);
+        assert!(input.button_a && input.button_up && input.button_l);
+        assert!(!input.button_b && !input.button_start);
+        assert_eq!(input.main_stick, Stick::new(0x10, 0xF0));
+        assert_eq!((input.left_trigger, input.right_trigger), (0x00, 0xFF));
+        assert_eq!(reports[1], PortReport::default());
+
+        payload[0] = 0x00;
+        assert_eq!(decode(&payload), Err(DecodeError::ReportId(0x00)));
+    }
+
+    #[test]
+    fn reports_round_trip() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+
+        for _ in 0..1000 {
+            let reports = decode(&rng.payload()).unwrap();
+            assert_eq!(decode(&encode(&reports)).unwrap(), reports);
+        }
+    }
+
+    #[test]
+    fn encoding_is_canonical() {
+        let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+
+        for _ in 0..1000 {
+            let canonical = encode(&decode(&rng.payload()).unwrap());
+            assert_eq!(encode(&decode(&canonical).unwrap()), canonical);
+        }
+    }
+
+    #[test]
+    fn rumble_round_trips() {
+        let states = [Rumble::On, Rumble::Off, Rumble::Off, Rumble::On];
+        let payload = encode_rumble(states);
+
+        assert_eq!(payload, [RUMBLE_REPORT_ID, 1, 0, 0, 1]);
+        assert_eq!(decode_rumble(&payload), Ok(states));
+    }
+}
@@ -1,6 +1,8 @@
 #![deny(clippy::all)]
 use serde::{Deserialize, Serialize};
 
+pub mod codec;
+
 pub const STICK_RANGE: StickRange = StickRange {
     center: 0x80,
     radius: 0x7F,
@@ -11,7 +13,7 @@ pub const TRIGGER_RANGE: AnalogRange = AnalogRange {
     max: 0xFF,
 };
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Rumble {
     Off,
     On,
@@ -52,7 +54,7 @@ pub struct AnalogRange {
     pub max: u8,
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Stick {
     pub x: u8,
     pub y: u8,
@@ -108,7 +110,7 @@ impl From<[u8; 2]> for Stick {
     }
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Input {
     pub button_a: bool,
     pub button_b: bool,
@@ -156,3 +158,78 @@ impl Default for Input {
         }
     }
 }
+
+impl Input {
+    #[must_use]
+    pub const fn button(&self, button: Button) -> bool {
+        match button {
+            Button::A => self.button_a,
+            Button::B => self.button_b,
+            Button::X => self.button_x,
+            Button::Y => self.button_y,
+            Button::Z => self.button_z,
+            Button::Start => self.button_start,
+            Button::L => self.button_l,
+            Button::R => self.button_r,
+            Button::Up => self.button_up,
+            Button::Down => self.button_down,
+            Button::Left => self.button_left,
+            Button::Right => self.button_right,
+        }
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        let field = match button {
+            Button::A => &mut self.button_a,
+            Button::B => &mut self.button_b,
+            Button::X => &mut self.button_x,
+            Button::Y => &mut self.button_y,
+            Button::Z => &mut self.button_z,
+            Button::Start => &mut self.button_start,
+            Button::L => &mut self.button_l,
+            Button::R => &mut self.button_r,
+            Button::Up => &mut self.button_up,
+            Button::Down => &mut self.button_down,
+            Button::Left => &mut self.button_left,
+            Button::Right => &mut self.button_right,
+        };
+
+        *field = pressed;
+    }
+}
+
+/// A digital button, where `L` and `R` are the triggers' clicks and the
+/// directions are the D-pad's.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Button {
+    A,
+    B,
+    X,
+    Y,
+    Z,
+    Start,
+    L,
+    R,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Button {
+    pub const ALL: [Self; 12] = [
+        Self::A,
+        Self::B,
+        Self::X,
+        Self::Y,
+        Self::Z,
+        Self::Start,
+        Self::L,
+        Self::R,
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+    ];
+}